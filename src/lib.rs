@@ -1,13 +1,24 @@
-use std::io;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicU8, AtomicUsize, Ordering},
     Arc,
 };
+use std::time::{Duration, Instant};
+use std::{fmt, io, ops};
+
+#[cfg(unix)]
+mod unix;
+mod wheel;
+
+#[cfg(unix)]
+pub use unix::{Source, SourceFd};
+
+pub use wheel::Timeout;
+use wheel::{millis_ceil, TimerWheel};
 
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::{Event, Registrator, Selector, TcpStream};
+pub use windows::{Event, Registrator, Selector, Source, TcpStream};
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -17,9 +28,13 @@ pub use macos::{Event, Registrator, Selector, TcpStream};
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "linux")]
-pub use linux::{Event, Registrator, Selector, TcpStream};
+pub use linux::{
+    pipe, Child, Clock, Event, Receiver, Registration, Registrator, Selector, Sender, SignalInfo,
+    Signals, StdioTokens, TcpListener, TcpStream, Timer, UdpSocket, UnixDatagram, UnixListener,
+    UnixSeqpacketListener, UnixStream, Waker, WatchDescriptor, WatchEvent, WatchEventKind,
+    WatchMask, Watcher,
+};
 
-pub type Events = Vec<Event>;
 pub type Token = usize;
 
 /// `Poll` represents the event queue. The `poll` method will block the current thread
@@ -31,66 +46,487 @@ pub type Token = usize;
 /// Alternatively, it can be used by waiting in one thread and registering interest in events from
 /// another. In this case you'll ned to call the `Poll::registrator()` method which returns a `Registrator`
 /// tied to this event queue which can be sent to another thread and used to register events.
+///
+/// Every registration chooses a `Trigger` that decides how often it reports readiness:
+///
+/// - `Trigger::Oneshot`: an event is reported once, then the registration is disarmed until
+///   it's re-armed with `reregister`. This is the safest choice when the thread handling the
+///   event is not the one polling.
+/// - `Trigger::Edge`: an event is reported each time the source *becomes* ready, and the
+///   registration stays armed. You must read (or write) until you get `WouldBlock` before
+///   waiting again, or you might never be notified about the data that is left.
+/// - `Trigger::Level`: an event is reported on every call to `poll` as long as the source
+///   is ready. Simple to use, but the same event is reported again if you don't consume it.
+///
+/// `Poll` also keeps a timer wheel for cheap in-process timeouts, see `insert_timeout`.
+///
+/// The event loop is shut down from a `Registrator`, either with `close_loop`, which lets the
+/// next `poll` return the events it got first, or with `abort_loop`, which doesn't. The token
+/// `usize::MAX` is used to wake up `poll` when that happens, so don't register sources with it.
+///
+/// Several threads can wait on the same event queue by giving each of them its own handle
+/// from `clone_handle`.
 #[derive(Debug)]
 pub struct Poll {
     registry: Registry,
-    is_poll_dead: Arc<AtomicBool>,
+    state: Arc<LoopState>,
+    timers: TimerWheel,
+    /// This handle has returned its last events after `close_loop`.
+    closed: bool,
 }
 
 impl Poll {
     pub fn new() -> io::Result<Poll> {
         Selector::new().map(|selector| Poll {
             registry: Registry { selector },
-            is_poll_dead: Arc::new(AtomicBool::new(false)),
+            state: Arc::new(LoopState::new()),
+            timers: TimerWheel::new(),
+            closed: false,
         })
     }
 
+    /// Creates another handle to the same event queue, so another thread can wait for events
+    /// at the same time. Sources registered through any handle, or any `Registrator`, are
+    /// reported to all of them. The timeouts from `insert_timeout` are not shared, they expire
+    /// on the handle they were inserted on.
+    ///
+    /// Every readiness change of a `Trigger::Edge` or `Trigger::Oneshot` registration wakes up
+    /// only one of the waiting threads. A `Trigger::Level` registration keeps waking threads
+    /// up for as long as the source is ready, so it's a bad fit here.
+    ///
+    /// On Linux `close_loop` and `abort_loop` shut down every handle. On macOS and Windows they
+    /// only wake up one waiting handle and the rest keep blocking in `poll`, so more than one
+    /// handle isn't supported there. The event queue stays usable until the last handle is
+    /// dropped.
+    pub fn clone_handle(&self) -> Poll {
+        self.state.add_poll();
+        Poll {
+            registry: Registry {
+                selector: self.registry.selector.clone(),
+            },
+            state: self.state.clone(),
+            timers: TimerWheel::new(),
+            closed: false,
+        }
+    }
+
+    /// Returns the `Registry` tied to this event queue. Use this to register interest
+    /// from the same thread that calls `poll`.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
     pub fn registrator(&self) -> Registrator {
-        self.registry
-            .selector
-            .registrator(self.is_poll_dead.clone())
+        self.registry.selector.registrator(self.state.clone())
+    }
+
+    /// Adds a timeout that expires once after `after`. The next call to `poll` after that
+    /// returns an event with `token` as its id and `is_timeout()` returning `true`.
+    ///
+    /// Timeouts have millisecond resolution and never expire early. They don't need a
+    /// registration and can share tokens with the I/O sources.
+    pub fn insert_timeout(&mut self, token: Token, after: Duration) -> Timeout {
+        self.timers.insert(token, after)
+    }
+
+    /// Cancels a timeout. Returns `false` if it already expired and was returned by `poll`.
+    pub fn cancel_timeout(&mut self, timeout: Timeout) -> bool {
+        self.timers.cancel(timeout)
     }
 
     /// Polls the event loop. The thread yields to the OS while witing for either
     /// an event to retur or a timeout to occur. A negative timeout will be treated
     /// as a timeout of 0.
-    pub fn poll(&mut self, events: &mut Events, timeout_ms: Option<i32>) -> io::Result<usize> {
+    ///
+    /// The thread also wakes up when the next timeout inserted with `insert_timeout` expires,
+    /// so there is no need to poll in a loop with a short timeout to check on them. Expired
+    /// timeouts are returned in `events` after the I/O events, as long as there is room.
+    ///
+    /// After `Registrator::close_loop` the events that were ready when `poll` woke up are still
+    /// returned, and every call after that returns `Error::Closed`. After
+    /// `Registrator::abort_loop` it returns `Error::Closed` right away, dropping any events.
+    /// A signal interrupting the wait is not an error, we just wait again.
+    pub fn poll(&mut self, events: &mut Events, timeout_ms: Option<i32>) -> Result<usize, Error> {
+        if self.closed || self.state.load() == CLOSED {
+            events.clear();
+            return Err(Error::Closed);
+        }
+
         // A negative timout is converted to a 0 timeout
-        let timeout = timeout_ms.map(|n| if n < 0 { 0 } else { n });
+        let timeout = timeout_ms.map(|n| Duration::from_millis(if n < 0 { 0 } else { n } as u64));
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
         loop {
-            let res = self.registry.selector.select(events, timeout);
+            let now = Instant::now();
+            let timeout = match (deadline, self.timers.next_timeout()) {
+                (Some(deadline), Some(next)) => Some(next.min(deadline - now.min(deadline))),
+                (Some(deadline), None) => Some(deadline.saturating_duration_since(now)),
+                (None, next) => next,
+            };
+
+            let res = self
+                .registry
+                .selector
+                .select(events, timeout.map(to_timeout_ms));
             match res {
-                Ok(()) => break,
-                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
-                Err(e) => return Err(e),
+                Ok(()) => (),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
             };
+
+            self.timers.expire(events);
+
+            // We might wake up for a timeout that isn't due yet since the timer wheel only
+            // knows when it needs to look at it again, keep waiting in that case
+            let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if !events.is_empty() || timed_out {
+                break;
+            }
+        }
+
+        match self.state.load() {
+            OPEN => Ok(events.len()),
+            CLOSING => {
+                self.closed = true;
+                events.retain(|event| event.id() != CLOSE_TOKEN);
+                if events.is_empty() {
+                    Err(Error::Closed)
+                } else {
+                    Ok(events.len())
+                }
+            }
+            _ => Err(Error::Closed),
+        }
+    }
+}
+
+impl Drop for Poll {
+    /// The `Registrator`s keep the event queue itself alive, but registering anything with it
+    /// once nobody can poll it is a bug, so they return `Error::Closed` once the last handle
+    /// is dropped.
+    fn drop(&mut self) {
+        if self.state.remove_poll() {
+            self.state.set_closed();
         }
+    }
+}
+
+/// The token of the event `Registrator::close_loop` and `Registrator::abort_loop` wake up
+/// `Poll::poll` with. It's never returned from `poll`.
+pub(crate) const CLOSE_TOKEN: Token = usize::MAX;
+
+const OPEN: u8 = 0;
+/// `close_loop` was called, the next `poll` on every handle returns the events it got and
+/// closes that handle.
+const CLOSING: u8 = 1;
+const CLOSED: u8 = 2;
+
+/// Whether the event loop is running, shared by the `Poll` handles and their `Registrator`s.
+#[derive(Debug)]
+pub(crate) struct LoopState {
+    state: AtomicU8,
+    polls: AtomicUsize,
+}
 
-        if self.is_poll_dead.load(Ordering::SeqCst) {
-            return Err(io::Error::new(io::ErrorKind::Interrupted, "Poll closed."));
+impl LoopState {
+    pub(crate) fn new() -> Self {
+        LoopState {
+            state: AtomicU8::new(OPEN),
+            polls: AtomicUsize::new(1),
         }
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.load() == OPEN
+    }
+
+    /// Starts a graceful shutdown. Returns `false` if the loop was already shut down.
+    pub(crate) fn close(&self) -> bool {
+        self.state
+            .compare_exchange(OPEN, CLOSING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+
+    /// Shuts down right away. Returns `false` if the loop was already shut down.
+    pub(crate) fn abort(&self) -> bool {
+        self.state
+            .compare_exchange(OPEN, CLOSED, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+
+    fn set_closed(&self) {
+        self.state.store(CLOSED, Ordering::SeqCst);
+    }
+
+    fn load(&self) -> u8 {
+        self.state.load(Ordering::SeqCst)
+    }
+
+    fn add_poll(&self) {
+        self.polls.fetch_add(1, Ordering::SeqCst);
+    }
 
-        Ok(events.len())
+    /// Returns `true` if this was the last `Poll` handle.
+    fn remove_poll(&self) -> bool {
+        self.polls.fetch_sub(1, Ordering::SeqCst) == 1
     }
 }
 
+/// Rounds up to whole milliseconds so we don't wake up just before a timeout expires.
+fn to_timeout_ms(timeout: Duration) -> i32 {
+    millis_ceil(timeout).min(i32::MAX as u64) as i32
+}
+
 #[derive(Debug)]
 pub struct Registry {
     selector: Selector,
 }
 
+impl Registry {
+    pub(crate) fn selector(&self) -> &Selector {
+        &self.selector
+    }
+
+    /// Returns `Error::AlreadyRegistered` if the source is already registered.
+    pub fn register<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.selector.register(source, token, interests, trigger)
+    }
+
+    /// Changes the token, interests or trigger of a registered source. This is also used to
+    /// re-arm a `Trigger::Oneshot` registration after an event has been delivered for it.
+    ///
+    /// Returns `Error::NotRegistered` if the source is not registered.
+    pub fn reregister<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.selector.reregister(source, token, interests, trigger)
+    }
+
+    /// Removes the source from the event queue.
+    ///
+    /// Returns `Error::NotRegistered` if the source is not registered.
+    pub fn deregister<S: Source + ?Sized>(&self, source: &mut S) -> Result<(), Error> {
+        self.selector.deregister(source)
+    }
+}
+
+/// The buffer `Poll::poll` fills with events. The OS is never handed more room than the
+/// capacity the buffer was created with, so a poll returns at most `capacity` events and
+/// the rest are returned by the next call.
+///
+/// A buffer created with `Events::growable` doubles its capacity every time a poll fills
+/// it completely, so busy event loops get to handle more events per call.
+pub struct Events {
+    inner: Vec<Event>,
+    capacity: usize,
+    grow: bool,
+}
+
+impl Events {
+    /// A capacity of 0 is treated as 1 since the OS needs room for at least one event.
+    pub fn with_capacity(capacity: usize) -> Events {
+        let capacity = capacity.max(1);
+        Events {
+            inner: Vec::with_capacity(capacity),
+            capacity,
+            grow: false,
+        }
+    }
+
+    pub fn growable(capacity: usize) -> Events {
+        let mut events = Events::with_capacity(capacity);
+        events.grow = true;
+        events
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Event> {
+        self.inner.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Adds an event we made ourselves, like an expired timeout, after the ones from the OS.
+    pub(crate) fn push(&mut self, event: Event) {
+        debug_assert!(self.inner.len() < self.capacity);
+        self.inner.push(event);
+    }
+
+    pub(crate) fn retain(&mut self, f: impl FnMut(&Event) -> bool) {
+        self.inner.retain(f);
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut Event {
+        self.inner.as_mut_ptr()
+    }
+
+    /// Sets the number of events the OS wrote to the buffer.
+    ///
+    /// # Safety
+    /// The caller must make sure that the first `len` events are initialized, and `len`
+    /// can't be larger than `capacity()`.
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len <= self.capacity);
+        self.inner.set_len(len);
+
+        if self.grow && len == self.capacity {
+            self.inner.reserve_exact(self.capacity);
+            self.capacity *= 2;
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Events {
+    type Item = &'a Event;
+    type IntoIter = std::slice::Iter<'a, Event>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl ops::Index<usize> for Events {
+    type Output = Event;
+
+    fn index(&self, index: usize) -> &Event {
+        &self.inner[index]
+    }
+}
+
+/// The errors returned when polling and registering sources. Errors from the OS that don't have
+/// a variant of their own are passed on as `Error::Io`.
+///
+/// It converts into an `io::Error` so `?` works in functions returning `io::Result`.
+/// `AlreadyRegistered` becomes an error of kind `AlreadyExists`, `NotRegistered` of kind
+/// `NotFound` and `Closed` of kind `Other`.
+#[derive(Debug)]
+pub enum Error {
+    /// The event loop was shut down from a `Registrator` or its `Poll` was dropped.
+    /// Nothing can be registered with it anymore and `Poll::poll` returns this instead of
+    /// waiting.
+    Closed,
+    /// The source is already registered with this event queue, use `reregister` to change it.
+    AlreadyRegistered,
+    /// The source is not registered with this event queue.
+    NotRegistered,
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Closed => f.write_str("Poll instance closed."),
+            Error::AlreadyRegistered => f.write_str("Source is already registered."),
+            Error::NotRegistered => f.write_str("Source is not registered."),
+            Error::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e {
+            Error::Io(e) => return e,
+            Error::Closed => io::ErrorKind::Other,
+            Error::AlreadyRegistered => io::ErrorKind::AlreadyExists,
+            Error::NotRegistered => io::ErrorKind::NotFound,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// Decides how a registration reports readiness. See `Poll` for the semantics of each mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Trigger {
+    #[default]
+    Oneshot,
+    Edge,
+    Level,
+}
+
 const WRITABLE: u8 = 0b0000_0001;
 const READABLE: u8 = 0b0000_0010;
+const PRIORITY: u8 = 0b0000_0100;
+const READ_CLOSED: u8 = 0b0000_1000;
+const EXCLUSIVE: u8 = 0b0001_0000;
 
-/// Represents interest in either Read or Write events. This struct is created
-/// by using one of the two constants:
+/// Represents interest in one or more kinds of events. This struct is created
+/// by using one of the constants and combining them with `|`:
 ///
 /// - Interests::READABLE
 /// - Interests::WRITABLE
+/// - Interests::PRIORITY: out-of-band data is available
+/// - Interests::READ_CLOSED: the peer closed its writing half of the connection
+/// - Interests::EXCLUSIVE: not an event but a flag, see below
+///
+/// `READABLE` always includes being notified when the peer closes the connection.
+///
+/// `EXCLUSIVE` is for when the same source is registered with several event queues, like a
+/// listener shared by threads that each have their own `Poll`. Only one of the `Poll`s that
+/// are waiting is woken up per event instead of all of them. It can only be combined with
+/// `READABLE` and `WRITABLE`, can't be used with `Trigger::Oneshot` and the registration
+/// can't be changed with `reregister`. It uses `EPOLLEXCLUSIVE` on Linux and is ignored on
+/// the other platforms.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Interests(u8);
 impl Interests {
     pub const READABLE: Interests = Interests(READABLE);
     pub const WRITABLE: Interests = Interests(WRITABLE);
+    pub const PRIORITY: Interests = Interests(PRIORITY);
+    pub const READ_CLOSED: Interests = Interests(READ_CLOSED);
+    pub const EXCLUSIVE: Interests = Interests(EXCLUSIVE);
+
+    /// Returns the union of `self` and `other`. Same as `self | other` but usable in a
+    /// `const` context.
+    pub const fn add(self, other: Interests) -> Interests {
+        Interests(self.0 | other.0)
+    }
+
+    /// Removes `other` from `self`. Returns `None` if no interests are left since a
+    /// registration without any interests makes no sense.
+    pub fn remove(self, other: Interests) -> Option<Interests> {
+        match self.0 & !other.0 {
+            n if n & !EXCLUSIVE == 0 => None,
+            n => Some(Interests(n)),
+        }
+    }
 
     pub fn is_readable(&self) -> bool {
         self.0 & READABLE != 0
@@ -99,4 +535,53 @@ impl Interests {
     pub fn is_writable(&self) -> bool {
         self.0 & WRITABLE != 0
     }
+
+    pub fn is_priority(&self) -> bool {
+        self.0 & PRIORITY != 0
+    }
+
+    pub fn is_read_closed(&self) -> bool {
+        self.0 & READ_CLOSED != 0
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        self.0 & EXCLUSIVE != 0
+    }
+}
+
+impl ops::BitOr for Interests {
+    type Output = Interests;
+
+    fn bitor(self, other: Interests) -> Interests {
+        self.add(other)
+    }
+}
+
+impl ops::BitOrAssign for Interests {
+    fn bitor_assign(&mut self, other: Interests) {
+        *self = self.add(other);
+    }
+}
+
+impl fmt::Debug for Interests {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.is_readable(), "READABLE"),
+            (self.is_writable(), "WRITABLE"),
+            (self.is_priority(), "PRIORITY"),
+            (self.is_read_closed(), "READ_CLOSED"),
+            (self.is_exclusive(), "EXCLUSIVE"),
+        ];
+
+        let mut first = true;
+        for (_, name) in names.iter().filter(|(is_set, _)| *is_set) {
+            if !first {
+                f.write_str(" | ")?;
+            }
+            f.write_str(name)?;
+            first = false;
+        }
+
+        Ok(())
+    }
 }
@@ -1,74 +1,193 @@
-use crate::{Events, Interests, Token};
-use std::io::{self, IoSliceMut, Read, Write};
+use crate::unix::Source;
+use crate::{Error, Events, Interests, LoopState, Registry, Token, Trigger, CLOSE_TOKEN};
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
 use std::net;
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::SocketAddr as UnixSocketAddr;
+use std::path::Path;
+use std::sync::Arc;
 
+mod child;
+mod pipe;
+mod signal;
+mod timer;
+mod udp;
+mod uds;
+mod watcher;
+
+pub use child::{Child, StdioTokens};
+pub use pipe::{pipe, Receiver, Sender};
+pub use signal::{SignalInfo, Signals};
+pub use timer::{Clock, Timer};
+pub use udp::UdpSocket;
+pub use uds::{UnixDatagram, UnixListener, UnixSeqpacketListener, UnixStream};
+pub use watcher::{WatchDescriptor, WatchEvent, WatchEventKind, WatchMask, Watcher};
+
+/// Where and how a source is registered. It's handed to `Source::registered`, so a source of
+/// your own can keep it and call `rearm` when it's done with an event, like the I/O types in
+/// this crate do when they return `WouldBlock`.
+#[derive(Debug, Clone)]
+pub struct Registration {
+    selector: Arc<SelectorFd>,
+    token: Token,
+    interests: Interests,
+    trigger: Trigger,
+}
+
+impl Registration {
+    /// Re-arms a `Trigger::Oneshot` registration so the next readiness change is reported.
+    /// Edge and level triggered registrations never get disarmed so there is nothing to do.
+    pub fn rearm(&self, fd: RawFd) -> Result<(), Error> {
+        match self.trigger {
+            Trigger::Oneshot => epoll_reregister(
+                self.selector.epfd,
+                fd,
+                self.token,
+                self.interests,
+                self.trigger,
+            ),
+            Trigger::Edge | Trigger::Level => Ok(()),
+        }
+    }
+}
+
+/// Registers sources with a `Poll` from any thread. It's cheap to clone, so every thread that
+/// needs one can have its own, and they can all be used at the same time. It keeps the epoll
+/// instance open, but once the `Poll` is dropped every registration fails with `Error::Closed`.
+#[derive(Debug, Clone)]
 pub struct Registrator {
-    fd: RawFd,
-    is_poll_dead: Arc<AtomicBool>,
+    fd: Arc<SelectorFd>,
+    state: Arc<LoopState>,
 }
 
 impl Registrator {
-    pub fn register(
+    pub fn register<S: Source + ?Sized>(
         &self,
-        stream: &TcpStream,
-        token: usize,
+        source: &mut S,
+        token: Token,
         interests: Interests,
-    ) -> io::Result<()> {
-        if self.is_poll_dead.load(Ordering::SeqCst) {
-            return Err(io::Error::new(
-                io::ErrorKind::Interrupted,
-                "Poll instance closed.",
-            ));
-        }
-        let fd = stream.as_raw_fd();
-        if interests.is_readable() {
-            // We register the id (or most oftenly referred to as a Token) to the `udata` field
-            // if the `Kevent`
-            let mut event = ffi::Event::new(ffi::EPOLLIN | ffi::EPOLLONESHOT, token);
-            epoll_ctl(self.fd, ffi::EPOLL_CTL_ADD, fd, &mut event)?;
-        };
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        register_source(&self.fd, source, token, interests, trigger)
+    }
 
-        if interests.is_writable() {
-            unimplemented!();
+    /// Changes the token, interests and trigger of an already registered source. This is
+    /// also how you re-arm a `Trigger::Oneshot` registration after it has reported an event.
+    pub fn reregister<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        reregister_source(&self.fd, source, token, interests, trigger)
+    }
+
+    /// Removes the source from the event queue. No more events will be reported for it.
+    pub fn deregister<S: Source + ?Sized>(&self, source: &mut S) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        deregister_source(&self.fd, source)
+    }
+
+    fn check_poll_alive(&self) -> Result<(), Error> {
+        if !self.state.is_open() {
+            return Err(Error::Closed);
         }
 
         Ok(())
     }
 
-    pub fn close_loop(&self) -> io::Result<()> {
-        if self
-            .is_poll_dead
-            .compare_and_swap(false, true, Ordering::SeqCst)
-        {
-            return Err(io::Error::new(
-                io::ErrorKind::Interrupted,
-                "Poll instance closed.",
-            ));
+    /// Shuts down the event loop gracefully. The `Poll` wakes up and still returns the events
+    /// that were ready, then `Error::Closed` from every call after that.
+    ///
+    /// Returns `Error::Closed` if the loop is already shut down.
+    pub fn close_loop(&self) -> Result<(), Error> {
+        if !self.state.close() {
+            return Err(Error::Closed);
         }
 
-        // This is a little hacky but works for our needs right now
-        let wake_fd = eventfd(1, 0)?;
-        let mut event = ffi::Event::new(ffi::EPOLLIN, 0);
-        epoll_ctl(self.fd, ffi::EPOLL_CTL_ADD, wake_fd, &mut event)?;
+        self.wake_poll()
+    }
+
+    /// Shuts down the event loop right away. The `Poll` wakes up and returns `Error::Closed`,
+    /// events that were ready are dropped.
+    ///
+    /// Returns `Error::Closed` if the loop is already shut down.
+    pub fn abort_loop(&self) -> Result<(), Error> {
+        if !self.state.abort() {
+            return Err(Error::Closed);
+        }
 
+        self.wake_poll()
+    }
+
+    /// The loop is only shut down once, so the counter of the eventfd can't overflow.
+    fn wake_poll(&self) -> Result<(), Error> {
+        (&self.fd.close).write_all(&1_u64.to_ne_bytes())?;
         Ok(())
     }
 }
 
+/// The epoll file descriptor. It's shared by the `Selector`, its `Registrator`s and the
+/// `Registration` of every source, and closed when the last of them is dropped, so none of
+/// them can end up using an fd number the OS has handed out to something else.
 #[derive(Debug)]
+struct SelectorFd {
+    epfd: RawFd,
+    /// The eventfd `close_loop` and `abort_loop` wake up `poll` with. It's registered level
+    /// triggered under `CLOSE_TOKEN`, so once it's written to every `Poll` handle waiting on
+    /// the queue wakes up, not just one. It's closed together with the epoll fd.
+    close: File,
+}
+
+impl SelectorFd {
+    fn new() -> io::Result<Self> {
+        let close = eventfd(0, ffi::EFD_CLOEXEC | ffi::EFD_NONBLOCK)?;
+        let close = unsafe { File::from_raw_fd(close) };
+        let fd = SelectorFd {
+            epfd: epoll_create()?,
+            close,
+        };
+
+        let mut event = ffi::Event::new(ffi::EPOLLIN, CLOSE_TOKEN);
+        epoll_ctl(
+            fd.epfd,
+            ffi::EPOLL_CTL_ADD,
+            fd.close.as_raw_fd(),
+            &mut event,
+        )?;
+        Ok(fd)
+    }
+}
+
+impl Drop for SelectorFd {
+    fn drop(&mut self) {
+        match close_fd(self.epfd) {
+            Ok(..) => (),
+            Err(e) => {
+                if !std::thread::panicking() {
+                    panic!("{}", e);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Selector {
-    fd: RawFd,
+    fd: Arc<SelectorFd>,
 }
 
 impl Selector {
     pub fn new() -> io::Result<Self> {
         Ok(Selector {
-            fd: epoll_create()?,
+            fd: Arc::new(SelectorFd::new()?),
         })
     }
 
@@ -77,68 +196,235 @@ impl Selector {
     pub fn select(&self, events: &mut Events, timeout_ms: Option<i32>) -> io::Result<()> {
         events.clear();
         let timeout = timeout_ms.unwrap_or(-1);
-        epoll_wait(self.fd, events, 1024, timeout).map(|n_events| {
-            // This is safe because `syscall_kevent` ensures that `n_events` are
-            // assigned. We could check for a valid token for each event to verify so this is
-            // just a performance optimization used in `mio` and copied here.
+        epoll_wait(self.fd.epfd, events, timeout).map(|n_events| {
+            // This is safe because `epoll_wait` ensures that `n_events` are
+            // assigned, and it never writes more than the capacity we gave it. We could check
+            // for a valid token for each event to verify so this is just a performance
+            // optimization used in `mio` and copied here.
             unsafe { events.set_len(n_events as usize) };
         })
     }
 
-    pub fn registrator(&self, is_poll_dead: Arc<AtomicBool>) -> Registrator {
+    pub(crate) fn registrator(&self, state: Arc<LoopState>) -> Registrator {
         Registrator {
-            fd: self.fd,
-            is_poll_dead,
+            fd: self.fd.clone(),
+            state,
         }
     }
+
+    pub fn register<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        register_source(&self.fd, source, token, interests, trigger)
+    }
+
+    pub fn reregister<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        reregister_source(&self.fd, source, token, interests, trigger)
+    }
+
+    pub fn deregister<S: Source + ?Sized>(&self, source: &mut S) -> Result<(), Error> {
+        deregister_source(&self.fd, source)
+    }
 }
 
-impl Drop for Selector {
-    fn drop(&mut self) {
-        match close_fd(self.fd) {
-            Ok(..) => (),
-            Err(e) => {
-                if !std::thread::panicking() {
-                    panic!(e);
-                }
+/// Wakes up a `Poll` blocking in another thread. The `Poll` will return an event with the
+/// token the `Waker` was created with, and keeps running as normal afterwards, so a `Waker`
+/// can be used any number of times (e.g. to tell the poll thread there is new work in a queue).
+///
+/// It's backed by a single `eventfd` registered as edge triggered. Every `wake` increases the
+/// counter of the `eventfd` which counts as a new edge, so the `Poll` is woken up once per
+/// `wake` call (or fewer if they're coalesced) and never spins on a counter nobody reads.
+#[derive(Debug)]
+pub struct Waker {
+    fd: File,
+}
+
+impl Waker {
+    pub fn new(registry: &Registry, token: Token) -> io::Result<Waker> {
+        let fd = eventfd(0, ffi::EFD_CLOEXEC | ffi::EFD_NONBLOCK)?;
+        // Wrapping it in a `File` gives us `Read`, `Write` and closing on drop for free
+        let fd = unsafe { File::from_raw_fd(fd) };
+        let mut event = ffi::Event::new(ffi::EPOLLIN | ffi::EPOLLET, token);
+        epoll_ctl(
+            registry.selector().fd.epfd,
+            ffi::EPOLL_CTL_ADD,
+            fd.as_raw_fd(),
+            &mut event,
+        )?;
+
+        Ok(Waker { fd })
+    }
+
+    pub fn wake(&self) -> io::Result<()> {
+        let buf = 1_u64.to_ne_bytes();
+        match (&self.fd).write(&buf) {
+            Ok(_) => Ok(()),
+            // The counter would overflow so we drain it and try again
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.reset()?;
+                self.wake()
             }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reading from an `eventfd` resets its counter to 0.
+    fn reset(&self) -> io::Result<()> {
+        let mut buf = [0; 8];
+        match (&self.fd).read(&mut buf) {
+            Ok(_) => Ok(()),
+            // Someone else already drained it
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(e),
         }
     }
 }
 
 pub type Event = ffi::Event;
 impl Event {
+    /// The events we make for expired timeouts have no readiness at all. The kernel only
+    /// reports file descriptors that have at least one event ready, so they can't be mixed up
+    /// with the events we get from `epoll_wait`, and every readiness accessor returns `false`.
+    pub(crate) fn timeout(token: Token) -> Event {
+        ffi::Event::new(0, token)
+    }
+
     pub fn id(&self) -> Token {
         self.data()
     }
+
+    /// A timeout inserted with `Poll::insert_timeout` expired.
+    pub fn is_timeout(&self) -> bool {
+        self.events() == 0
+    }
+
+    /// The file descriptor is ready for reading.
+    pub fn is_readable(&self) -> bool {
+        self.events() & ffi::EPOLLIN as u32 != 0
+    }
+
+    /// The file descriptor is ready for writing, i.e. there is room in the send buffer.
+    pub fn is_writable(&self) -> bool {
+        self.events() & ffi::EPOLLOUT as u32 != 0
+    }
+
+    /// An error condition happened on the file descriptor. The next read or write on the
+    /// source returns the error. Always reported, even if not asked for.
+    pub fn is_error(&self) -> bool {
+        self.events() & ffi::EPOLLERR as u32 != 0
+    }
+
+    /// The peer closed its writing half of the connection (`EPOLLRDHUP`), so a read will
+    /// return the data that is left and then `0`.
+    pub fn is_read_closed(&self) -> bool {
+        self.events() & ffi::EPOLLRDHUP as u32 != 0
+    }
+
+    /// The connection is closed in both directions (`EPOLLHUP`) and we can't write to it
+    /// anymore. Always reported, even if not asked for.
+    pub fn is_write_closed(&self) -> bool {
+        self.events() & ffi::EPOLLHUP as u32 != 0
+    }
+
+    /// There is priority (out-of-band) data to read (`EPOLLPRI`).
+    pub fn is_priority(&self) -> bool {
+        self.events() & ffi::EPOLLPRI as u32 != 0
+    }
+}
+
+/// A `WouldBlock` error means we've read everything there is (or filled up the buffer when
+/// writing) for now. If the source is registered as `Trigger::Oneshot` it was disarmed when it
+/// reported the event that made us try, so we re-arm it to get notified once it's ready again.
+fn rearm_on_would_block<T>(
+    registration: &Option<Registration>,
+    fd: RawFd,
+    res: io::Result<T>,
+) -> io::Result<T> {
+    match res {
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+            if let Some(registration) = registration {
+                registration.rearm(fd)?;
+            }
+            res
+        }
+        res => res,
+    }
 }
 
+#[derive(Debug)]
 pub struct TcpStream {
     inner: net::TcpStream,
+    registration: Option<Registration>,
 }
 
 impl TcpStream {
+    /// Starts connecting to `adr` and returns immediately. The socket is created non-blocking
+    /// before we call `connect` so the handshake happens in the background (a DNS lookup, if
+    /// `adr` needs one, still blocks). Only the first address `adr` resolves to is tried.
+    ///
+    /// Register the stream with `Interests::WRITABLE` to get notified when the connection is
+    /// established or has failed, then call `take_error` to find out which one it was.
     pub fn connect(adr: impl net::ToSocketAddrs) -> io::Result<Self> {
-        // actually we should set this to non-blocking before we call connect which is not something
-        // we get from the stdlib but could do with a syscall. Let's skip that step in this example.
-        // In other words this will block shortly establishing a connection to the remote server
-        let stream = net::TcpStream::connect(adr)?;
-        stream.set_nonblocking(true)?;
+        let adr = adr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "No addresses to connect to.")
+        })?;
+
+        let domain = match adr {
+            net::SocketAddr::V4(..) => ffi::AF_INET,
+            net::SocketAddr::V6(..) => ffi::AF_INET6,
+        };
+
+        let fd = socket(
+            domain,
+            ffi::SOCK_STREAM | ffi::SOCK_NONBLOCK | ffi::SOCK_CLOEXEC,
+        )?;
+        // We let the standard library own the socket right away so it's closed if connecting fails
+        let stream = unsafe { net::TcpStream::from_raw_fd(fd) };
+
+        let (adr, len) = to_sockaddr(&adr);
+        match connect(fd, &adr, len) {
+            Ok(()) => (),
+            Err(ref e) if e.raw_os_error() == Some(ffi::EINPROGRESS) => (),
+            Err(e) => return Err(e),
+        }
+
+        Ok(TcpStream {
+            inner: stream,
+            registration: None,
+        })
+    }
+
+    /// Returns and clears the pending error on the socket (`SO_ERROR`). After a writable event
+    /// on a connecting stream, `Ok(None)` means the connection was established.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
 
-        Ok(TcpStream { inner: stream })
+    pub fn peer_addr(&self) -> io::Result<net::SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    pub fn local_addr(&self) -> io::Result<net::SocketAddr> {
+        self.inner.local_addr()
     }
 }
 
 impl Read for TcpStream {
+    /// The socket is non-blocking, so this returns an error of kind `WouldBlock` when there is
+    /// no more data to read right now. Wait for the next readable event and read again.
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        // If we let the socket operate non-blocking we could get an error of kind `WouldBlock`,
-        // that means there is more data to read but we would block if we waited for it to arrive.
-        // The right thing to do is to re-register the event, getting notified once more
-        // data is available. We'll not do that in our implementation since we're making an example
-        // and instead we make the socket blocking again while we read from it
-        self.inner.set_nonblocking(false)?;
-
-        (&self.inner).read(buf)
+        let res = (&self.inner).read(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
     }
 
     /// Copies data to fill each buffer in order, with the final buffer possibly only beeing
@@ -148,13 +434,22 @@ impl Read for TcpStream {
     /// IoSliceMut is like `&mut [u8]` but it's guaranteed to be ABI compatible with the `iovec`
     /// type on unix platforms and `WSABUF` on Windows. Perfect for us.
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
-        (&self.inner).read_vectored(bufs)
+        let res = (&self.inner).read_vectored(bufs);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
     }
 }
 
 impl Write for TcpStream {
+    /// Returns an error of kind `WouldBlock` when the send buffer is full. Wait for the next
+    /// writable event and write again.
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.write(buf)
+        let res = (&self.inner).write(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        let res = (&self.inner).write_vectored(bufs);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -168,14 +463,244 @@ impl AsRawFd for TcpStream {
     }
 }
 
+impl Source for TcpStream {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+/// A non-blocking TCP socket listening for connections. Register it with `Interests::READABLE`
+/// and a readable event means there are connections waiting to be accepted.
+#[derive(Debug)]
+pub struct TcpListener {
+    inner: net::TcpListener,
+    registration: Option<Registration>,
+}
+
+impl TcpListener {
+    pub fn bind(adr: impl net::ToSocketAddrs) -> io::Result<Self> {
+        // The standard library creates the socket with `SOCK_CLOEXEC`
+        let listener = net::TcpListener::bind(adr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(TcpListener {
+            inner: listener,
+            registration: None,
+        })
+    }
+
+    /// Accepts a pending connection. Returns an error of kind `WouldBlock` if there are no
+    /// connections waiting, in which case you should wait for the next readable event.
+    ///
+    /// The returned stream is non-blocking and close-on-exec from the start.
+    pub fn accept(&self) -> io::Result<(TcpStream, net::SocketAddr)> {
+        let res = accept4(
+            self.inner.as_raw_fd(),
+            ffi::SOCK_NONBLOCK | ffi::SOCK_CLOEXEC,
+        );
+        let fd = rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)?;
+        let stream = unsafe { net::TcpStream::from_raw_fd(fd) };
+        let adr = stream.peer_addr()?;
+
+        Ok((
+            TcpStream {
+                inner: stream,
+                registration: None,
+            },
+            adr,
+        ))
+    }
+
+    pub fn local_addr(&self) -> io::Result<net::SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+impl AsRawFd for TcpListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for TcpListener {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
 mod ffi {
-    use std::io;
-    use std::os::raw::c_void;
+    use std::os::raw::c_char;
 
+    pub const EPOLL_CLOEXEC: i32 = 0o2000000;
     pub const EPOLL_CTL_ADD: i32 = 1;
     pub const EPOLL_CTL_DEL: i32 = 2;
+    pub const EPOLL_CTL_MOD: i32 = 3;
     pub const EPOLLIN: i32 = 0x1;
+    pub const EPOLLPRI: i32 = 0x2;
+    pub const EPOLLOUT: i32 = 0x4;
+    pub const EPOLLERR: i32 = 0x8;
+    pub const EPOLLHUP: i32 = 0x10;
+    pub const EPOLLRDHUP: i32 = 0x2000;
     pub const EPOLLONESHOT: i32 = 0x40000000;
+    pub const EPOLLET: i32 = 0x80000000u32 as i32;
+    pub const EPOLLEXCLUSIVE: i32 = 0x10000000;
+
+    pub const EFD_CLOEXEC: i32 = 0o2000000;
+    pub const EFD_NONBLOCK: i32 = 0o4000;
+
+    pub const O_NONBLOCK: i32 = 0o4000;
+    pub const O_CLOEXEC: i32 = 0o2000000;
+    pub const F_GETFL: i32 = 3;
+    pub const F_SETFL: i32 = 4;
+
+    pub const SYS_PIDFD_OPEN: i64 = 434;
+
+    pub const CLOCK_REALTIME: i32 = 0;
+    pub const CLOCK_MONOTONIC: i32 = 1;
+    pub const TFD_NONBLOCK: i32 = 0o4000;
+    pub const TFD_CLOEXEC: i32 = 0o2000000;
+    pub const TFD_TIMER_ABSTIME: i32 = 1;
+    pub const TFD_TIMER_CANCEL_ON_SET: i32 = 2;
+
+    pub const IN_NONBLOCK: i32 = 0o4000;
+    pub const IN_CLOEXEC: i32 = 0o2000000;
+    pub const IN_MODIFY: u32 = 0x2;
+    pub const IN_ATTRIB: u32 = 0x4;
+    pub const IN_CLOSE_WRITE: u32 = 0x8;
+    pub const IN_MOVED_FROM: u32 = 0x40;
+    pub const IN_MOVED_TO: u32 = 0x80;
+    pub const IN_CREATE: u32 = 0x100;
+    pub const IN_DELETE: u32 = 0x200;
+    pub const IN_DELETE_SELF: u32 = 0x400;
+    pub const IN_MOVE_SELF: u32 = 0x800;
+    pub const IN_UNMOUNT: u32 = 0x2000;
+    pub const IN_Q_OVERFLOW: u32 = 0x4000;
+    pub const IN_IGNORED: u32 = 0x8000;
+    pub const IN_ISDIR: u32 = 0x40000000;
+
+    pub const SIG_BLOCK: i32 = 0;
+    pub const SFD_NONBLOCK: i32 = 0o4000;
+    pub const SFD_CLOEXEC: i32 = 0o2000000;
+
+    pub const AF_UNIX: i32 = 1;
+    pub const AF_INET: i32 = 2;
+    pub const AF_INET6: i32 = 10;
+    pub const SOCK_STREAM: i32 = 1;
+    pub const SOCK_SEQPACKET: i32 = 5;
+    pub const SOCK_NONBLOCK: i32 = 0o4000;
+    pub const SOCK_CLOEXEC: i32 = 0o2000000;
+
+    pub const ENOENT: i32 = 2;
+    pub const EEXIST: i32 = 17;
+    pub const EINPROGRESS: i32 = 115;
+    pub const ECANCELED: i32 = 125;
+
+    /// `struct sockaddr_in` from `netinet/in.h`. The port and address are in network byte order.
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    pub struct SockaddrIn {
+        pub sin_family: u16,
+        pub sin_port: u16,
+        pub sin_addr: u32,
+        pub sin_zero: [u8; 8],
+    }
+
+    /// `struct sockaddr_in6` from `netinet/in.h`.
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    pub struct SockaddrIn6 {
+        pub sin6_family: u16,
+        pub sin6_port: u16,
+        pub sin6_flowinfo: u32,
+        pub sin6_addr: [u8; 16],
+        pub sin6_scope_id: u32,
+    }
+
+    /// `struct sockaddr_un` from `sys/un.h`. A path is nul terminated, an abstract name
+    /// starts with a nul byte instead and its length is given by the address length.
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    pub struct SockaddrUn {
+        pub sun_family: u16,
+        pub sun_path: [u8; 108],
+    }
+
+    /// `struct timespec` from `time.h`.
+    #[derive(Clone, Copy, Default)]
+    #[repr(C)]
+    pub struct Timespec {
+        pub tv_sec: i64,
+        pub tv_nsec: i64,
+    }
+
+    /// `struct itimerspec` from `time.h`. A zero `it_interval` makes the timer fire once, a zero
+    /// `it_value` disarms it.
+    #[derive(Clone, Copy, Default)]
+    #[repr(C)]
+    pub struct Itimerspec {
+        pub it_interval: Timespec,
+        pub it_value: Timespec,
+    }
+
+    /// `sigset_t` from `signal.h` is 1024 bits in glibc. Signal `n` is bit `n - 1`.
+    #[derive(Clone, Copy, Default)]
+    #[repr(C)]
+    pub struct Sigset {
+        pub val: [u64; 16],
+    }
+
+    /// `struct signalfd_siginfo` from `sys/signalfd.h`. What's filled in depends on the signal.
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    pub struct SignalfdSiginfo {
+        pub ssi_signo: u32,
+        pub ssi_errno: i32,
+        pub ssi_code: i32,
+        pub ssi_pid: u32,
+        pub ssi_uid: u32,
+        pub ssi_fd: i32,
+        pub ssi_tid: u32,
+        pub ssi_band: u32,
+        pub ssi_overrun: u32,
+        pub ssi_trapno: u32,
+        pub ssi_status: i32,
+        pub ssi_int: i32,
+        pub ssi_ptr: u64,
+        pub ssi_utime: u64,
+        pub ssi_stime: u64,
+        pub ssi_addr: u64,
+        pub ssi_addr_lsb: u16,
+        pub pad2: u16,
+        pub ssi_syscall: i32,
+        pub ssi_call_addr: u64,
+        pub ssi_arch: u32,
+        pub pad: [u8; 28],
+    }
+
+    /// The kernel takes a pointer to a generic `sockaddr` and reads the family field to
+    /// find out which of these it actually got.
+    #[repr(C)]
+    pub union SockAddr {
+        pub v4: SockaddrIn,
+        pub v6: SockaddrIn6,
+        pub un: SockaddrUn,
+    }
 
     /// Since the same name is used multiple times, it can be confusing but we have an `Event` structure.
     /// This structure ties a file descriptor and a field called `events` together. The field `events` holds information
@@ -197,12 +722,16 @@ mod ffi {
         pub fn data(&self) -> usize {
             self.epoll_data
         }
+
+        pub fn events(&self) -> u32 {
+            self.events
+        }
     }
 
     #[link(name = "c")]
     extern "C" {
         /// http://man7.org/linux/man-pages/man2/epoll_create1.2.html
-        pub fn epoll_create(size: i32) -> i32;
+        pub fn epoll_create1(flags: i32) -> i32;
 
         /// http://man7.org/linux/man-pages/man2/close.2.html
         pub fn close(fd: i32) -> i32;
@@ -216,14 +745,73 @@ mod ffi {
         /// - timeout of -1 means indefinite
         pub fn epoll_wait(epfd: i32, events: *mut Event, maxevents: i32, timeout: i32) -> i32;
 
-        /// http://man7.org/linux/man-pages/man2/timerfd_create.2.html
+        /// http://man7.org/linux/man-pages/man2/eventfd.2.html
         pub fn eventfd(initva: u32, flags: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/timerfd_create.2.html
+        pub fn timerfd_create(clockid: i32, flags: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/timerfd_settime.2.html
+        pub fn timerfd_settime(
+            fd: i32,
+            flags: i32,
+            new_value: *const Itimerspec,
+            old_value: *mut Itimerspec,
+        ) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/clock_gettime.2.html
+        pub fn clock_gettime(clockid: i32, tp: *mut Timespec) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/inotify_init1.2.html
+        pub fn inotify_init1(flags: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/inotify_add_watch.2.html
+        pub fn inotify_add_watch(fd: i32, pathname: *const c_char, mask: u32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/inotify_rm_watch.2.html
+        pub fn inotify_rm_watch(fd: i32, wd: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/signalfd.2.html
+        pub fn signalfd(fd: i32, mask: *const Sigset, flags: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man3/pthread_sigmask.3.html
+        ///
+        /// Returns the error number instead of setting `errno`.
+        pub fn pthread_sigmask(how: i32, set: *const Sigset, oldset: *mut Sigset) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/fcntl.2.html
+        pub fn fcntl(fd: i32, cmd: i32, ...) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/syscall.2.html
+        ///
+        /// Used for system calls older versions of glibc don't have a wrapper for.
+        pub fn syscall(number: i64, ...) -> i64;
+
+        /// http://man7.org/linux/man-pages/man2/pipe.2.html
+        pub fn pipe2(pipefd: *mut i32, flags: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/socket.2.html
+        pub fn socket(domain: i32, ty: i32, protocol: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/connect.2.html
+        pub fn connect(sockfd: i32, addr: *const SockAddr, addrlen: u32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/bind.2.html
+        pub fn bind(sockfd: i32, addr: *const SockAddr, addrlen: u32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/listen.2.html
+        pub fn listen(sockfd: i32, backlog: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/accept4.2.html
+        pub fn accept4(sockfd: i32, addr: *mut SockAddr, addrlen: *mut u32, flags: i32) -> i32;
+
+        /// http://man7.org/linux/man-pages/man2/socketpair.2.html
+        pub fn socketpair(domain: i32, ty: i32, protocol: i32, sv: *mut i32) -> i32;
     }
 }
 
 fn epoll_create() -> io::Result<i32> {
-    // Size argument is ignored but must be greater than zero
-    let res = unsafe { ffi::epoll_create(1) };
+    let res = unsafe { ffi::epoll_create1(ffi::EPOLL_CLOEXEC) };
     if res < 0 {
         Err(io::Error::last_os_error())
     } else {
@@ -249,10 +837,166 @@ fn epoll_ctl(epfd: i32, op: i32, fd: i32, event: &mut Event) -> io::Result<()> {
     }
 }
 
+fn epoll_flags(interests: &Interests, trigger: Trigger) -> i32 {
+    let mut flags = match trigger {
+        Trigger::Oneshot => ffi::EPOLLONESHOT,
+        Trigger::Edge => ffi::EPOLLET,
+        // Level triggered is the default behaviour of epoll
+        Trigger::Level => 0,
+    };
+
+    if interests.is_readable() {
+        flags |= ffi::EPOLLIN;
+        // We always ask for `EPOLLRDHUP` so a hangup can be told apart from data, except for
+        // exclusive registrations which don't allow it
+        if !interests.is_exclusive() {
+            flags |= ffi::EPOLLRDHUP;
+        }
+    }
+
+    if interests.is_writable() {
+        flags |= ffi::EPOLLOUT;
+    }
+
+    if interests.is_priority() {
+        flags |= ffi::EPOLLPRI;
+    }
+
+    if interests.is_read_closed() {
+        flags |= ffi::EPOLLRDHUP;
+    }
+
+    if interests.is_exclusive() {
+        flags |= ffi::EPOLLEXCLUSIVE;
+    }
+
+    flags
+}
+
+/// The kernel only says `EINVAL` if an exclusive registration isn't allowed, so we check
+/// ourselves to be able to tell why.
+fn check_exclusive(interests: &Interests, trigger: Trigger) -> Result<(), Error> {
+    if trigger == Trigger::Oneshot {
+        return Err(invalid_exclusive(
+            "Interests::EXCLUSIVE can't be used with Trigger::Oneshot.",
+        ));
+    }
+
+    if interests.is_priority() || interests.is_read_closed() {
+        return Err(invalid_exclusive(
+            "Interests::EXCLUSIVE can only be combined with READABLE and WRITABLE.",
+        ));
+    }
+
+    Ok(())
+}
+
+fn invalid_exclusive(msg: &str) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidInput, msg))
+}
+
+/// Both directions has to be registered in the same call since a second `EPOLL_CTL_ADD` on
+/// the same fd fails with `EEXIST`.
+fn epoll_register(
+    epfd: i32,
+    fd: i32,
+    token: Token,
+    interests: Interests,
+    trigger: Trigger,
+) -> Result<(), Error> {
+    if interests.is_exclusive() {
+        check_exclusive(&interests, trigger)?;
+    }
+
+    // We register the id (or most oftenly referred to as a Token) to the `epoll_data`
+    // field of the `Event`
+    let mut event = ffi::Event::new(epoll_flags(&interests, trigger), token);
+    epoll_ctl(epfd, ffi::EPOLL_CTL_ADD, fd, &mut event).map_err(|e| match e.raw_os_error() {
+        Some(ffi::EEXIST) => Error::AlreadyRegistered,
+        _ => Error::Io(e),
+    })
+}
+
+fn epoll_reregister(
+    epfd: i32,
+    fd: i32,
+    token: Token,
+    interests: Interests,
+    trigger: Trigger,
+) -> Result<(), Error> {
+    if interests.is_exclusive() {
+        return Err(invalid_exclusive(
+            "Exclusive registrations can't be changed, deregister and register again.",
+        ));
+    }
+
+    let mut event = ffi::Event::new(epoll_flags(&interests, trigger), token);
+    epoll_ctl(epfd, ffi::EPOLL_CTL_MOD, fd, &mut event).map_err(not_registered)
+}
+
+fn epoll_deregister(epfd: i32, fd: i32) -> Result<(), Error> {
+    // Since Linux 2.6.9 the event pointer is ignored for `EPOLL_CTL_DEL` but it can't be null
+    let mut event = ffi::Event::new(0, 0);
+    epoll_ctl(epfd, ffi::EPOLL_CTL_DEL, fd, &mut event).map_err(not_registered)
+}
+
+fn register_source<S: Source + ?Sized>(
+    selector: &Arc<SelectorFd>,
+    source: &mut S,
+    token: Token,
+    interests: Interests,
+    trigger: Trigger,
+) -> Result<(), Error> {
+    epoll_register(selector.epfd, source.raw_fd(), token, interests, trigger)?;
+    source.registered(Registration {
+        selector: selector.clone(),
+        token,
+        interests,
+        trigger,
+    });
+    Ok(())
+}
+
+fn reregister_source<S: Source + ?Sized>(
+    selector: &Arc<SelectorFd>,
+    source: &mut S,
+    token: Token,
+    interests: Interests,
+    trigger: Trigger,
+) -> Result<(), Error> {
+    epoll_reregister(selector.epfd, source.raw_fd(), token, interests, trigger)?;
+    source.registered(Registration {
+        selector: selector.clone(),
+        token,
+        interests,
+        trigger,
+    });
+    Ok(())
+}
+
+fn deregister_source<S: Source + ?Sized>(
+    selector: &Arc<SelectorFd>,
+    source: &mut S,
+) -> Result<(), Error> {
+    epoll_deregister(selector.epfd, source.raw_fd())?;
+    source.deregistered();
+    Ok(())
+}
+
+fn not_registered(e: io::Error) -> Error {
+    match e.raw_os_error() {
+        Some(ffi::ENOENT) => Error::NotRegistered,
+        _ => Error::Io(e),
+    }
+}
+
 /// Waits for events on the epoll instance to occur. Returns the number file descriptors ready for the requested I/O.
 /// When successful, epoll_wait() returns the number of file descriptors ready for the requested
 /// I/O, or zero if no file descriptor became ready during the requested timeout milliseconds
-fn epoll_wait(epfd: i32, events: &mut [Event], maxevents: i32, timeout: i32) -> io::Result<i32> {
+///
+/// `maxevents` is the capacity of `events`, so the kernel never writes past the end of the buffer.
+fn epoll_wait(epfd: i32, events: &mut Events, timeout: i32) -> io::Result<i32> {
+    let maxevents = events.capacity() as i32;
     let res = unsafe { ffi::epoll_wait(epfd, events.as_mut_ptr(), maxevents, timeout) };
     if res < 0 {
         Err(io::Error::last_os_error())
@@ -269,3 +1013,228 @@ fn eventfd(initva: u32, flags: i32) -> io::Result<i32> {
         Ok(res)
     }
 }
+
+fn timerfd_create(clockid: i32, flags: i32) -> io::Result<i32> {
+    let res = unsafe { ffi::timerfd_create(clockid, flags) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+fn timerfd_settime(fd: i32, flags: i32, new_value: &ffi::Itimerspec) -> io::Result<()> {
+    let res = unsafe { ffi::timerfd_settime(fd, flags, new_value, std::ptr::null_mut()) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn clock_gettime(clockid: i32) -> io::Result<ffi::Timespec> {
+    let mut tp = ffi::Timespec::default();
+    let res = unsafe { ffi::clock_gettime(clockid, &mut tp) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(tp)
+    }
+}
+
+fn inotify_init1(flags: i32) -> io::Result<i32> {
+    let res = unsafe { ffi::inotify_init1(flags) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+fn inotify_add_watch(fd: i32, path: &Path, mask: u32) -> io::Result<i32> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let res = unsafe { ffi::inotify_add_watch(fd, path.as_ptr(), mask) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+fn inotify_rm_watch(fd: i32, wd: i32) -> io::Result<()> {
+    let res = unsafe { ffi::inotify_rm_watch(fd, wd) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn signalfd(mask: &ffi::Sigset, flags: i32) -> io::Result<i32> {
+    let res = unsafe { ffi::signalfd(-1, mask, flags) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+fn pthread_sigmask(how: i32, set: &ffi::Sigset) -> io::Result<()> {
+    let res = unsafe { ffi::pthread_sigmask(how, set, std::ptr::null_mut()) };
+    if res != 0 {
+        Err(io::Error::from_raw_os_error(res))
+    } else {
+        Ok(())
+    }
+}
+
+fn set_nonblocking(fd: i32) -> io::Result<()> {
+    let flags = unsafe { ffi::fcntl(fd, ffi::F_GETFL) };
+    if flags < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let res = unsafe { ffi::fcntl(fd, ffi::F_SETFL, flags | ffi::O_NONBLOCK) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// http://man7.org/linux/man-pages/man2/pidfd_open.2.html
+///
+/// The file descriptor is always close-on-exec.
+fn pidfd_open(pid: u32) -> io::Result<i32> {
+    let res = unsafe { ffi::syscall(ffi::SYS_PIDFD_OPEN, pid as i32, 0) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res as i32)
+    }
+}
+
+/// Returns the read end first and the write end second.
+fn pipe2(flags: i32) -> io::Result<(i32, i32)> {
+    let mut fds = [0; 2];
+    let res = unsafe { ffi::pipe2(fds.as_mut_ptr(), flags) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok((fds[0], fds[1]))
+    }
+}
+
+fn socket(domain: i32, ty: i32) -> io::Result<i32> {
+    let res = unsafe { ffi::socket(domain, ty, 0) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+fn socketpair(domain: i32, ty: i32) -> io::Result<(i32, i32)> {
+    let mut fds = [0; 2];
+    let res = unsafe { ffi::socketpair(domain, ty, 0, fds.as_mut_ptr()) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok((fds[0], fds[1]))
+    }
+}
+
+fn connect(fd: i32, adr: &ffi::SockAddr, len: u32) -> io::Result<()> {
+    let res = unsafe { ffi::connect(fd, adr, len) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn bind(fd: i32, adr: &ffi::SockAddr, len: u32) -> io::Result<()> {
+    let res = unsafe { ffi::bind(fd, adr, len) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn listen(fd: i32, backlog: i32) -> io::Result<()> {
+    let res = unsafe { ffi::listen(fd, backlog) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// We don't ask for the peer address, the callers get it from the socket afterwards which
+/// saves us from converting every kind of `sockaddr` back.
+fn accept4(fd: i32, flags: i32) -> io::Result<i32> {
+    let res = unsafe { ffi::accept4(fd, std::ptr::null_mut(), std::ptr::null_mut(), flags) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+/// Converts a `SocketAddr` to the C representation. Returns the length of the struct actually
+/// used since that's what the kernel wants to know.
+fn to_sockaddr(adr: &net::SocketAddr) -> (ffi::SockAddr, u32) {
+    match adr {
+        net::SocketAddr::V4(adr) => {
+            let sockaddr = ffi::SockaddrIn {
+                sin_family: ffi::AF_INET as u16,
+                sin_port: adr.port().to_be(),
+                // The octets are already in network byte order
+                sin_addr: u32::from_ne_bytes(adr.ip().octets()),
+                sin_zero: [0; 8],
+            };
+            let len = std::mem::size_of::<ffi::SockaddrIn>() as u32;
+            (ffi::SockAddr { v4: sockaddr }, len)
+        }
+        net::SocketAddr::V6(adr) => {
+            let sockaddr = ffi::SockaddrIn6 {
+                sin6_family: ffi::AF_INET6 as u16,
+                sin6_port: adr.port().to_be(),
+                sin6_flowinfo: adr.flowinfo(),
+                sin6_addr: adr.ip().octets(),
+                sin6_scope_id: adr.scope_id(),
+            };
+            let len = std::mem::size_of::<ffi::SockaddrIn6>() as u32;
+            (ffi::SockAddr { v6: sockaddr }, len)
+        }
+    }
+}
+
+/// Same as `to_sockaddr` for Unix socket addresses. Paths get a nul terminator and abstract
+/// names (Linux only) a leading nul byte. An unnamed address is just the family field.
+fn to_sockaddr_un(adr: &UnixSocketAddr) -> io::Result<(ffi::SockAddr, u32)> {
+    let mut sockaddr = ffi::SockaddrUn {
+        sun_family: ffi::AF_UNIX as u16,
+        sun_path: [0; 108],
+    };
+
+    let (name, start, terminator) = if let Some(path) = adr.as_pathname() {
+        (path.as_os_str().as_bytes(), 0, 1)
+    } else if let Some(name) = adr.as_abstract_name() {
+        (name, 1, 0)
+    } else {
+        (&[][..], 0, 0)
+    };
+
+    if start + name.len() + terminator > sockaddr.sun_path.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Address is too long for a Unix socket.",
+        ));
+    }
+
+    sockaddr.sun_path[start..start + name.len()].copy_from_slice(name);
+    let len = std::mem::size_of::<u16>() + start + name.len() + terminator;
+    Ok((ffi::SockAddr { un: sockaddr }, len as u32))
+}
@@ -0,0 +1,150 @@
+use super::{pidfd_open, Receiver, Registration, Sender, Source};
+use crate::{Interests, Registry, Token, Trigger};
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::process::{self, Command, ExitStatus};
+
+/// A child process that can be registered with `Poll`. It's backed by a `pidfd` which becomes
+/// readable when the process exits, so there is no need for a `SIGCHLD` handler. Register it
+/// with `Interests::READABLE` and call `try_wait` after the event to get the exit status.
+///
+/// The pipes for the standard streams that were set to `Stdio::piped()` are available as
+/// non-blocking `Sender` and `Receiver`s which can be registered as well, or which
+/// `spawn_registered` registers for you.
+#[derive(Debug)]
+pub struct Child {
+    inner: process::Child,
+    pidfd: File,
+    registration: Option<Registration>,
+    pub stdin: Option<Sender>,
+    pub stdout: Option<Receiver>,
+    pub stderr: Option<Receiver>,
+}
+
+impl Child {
+    pub fn spawn(command: &mut Command) -> io::Result<Child> {
+        Child::from_std(command.spawn()?)
+    }
+
+    /// Spawns the child like `spawn` and registers the pipes of the standard streams that were
+    /// set to `Stdio::piped()`, `stdin` with `Interests::WRITABLE` and `stdout` and `stderr`
+    /// with `Interests::READABLE`. The child itself isn't registered, do that like with any
+    /// other source if you want to know when it exits.
+    ///
+    /// If a pipe can't be registered the child is killed before the error is returned.
+    pub fn spawn_registered(
+        command: &mut Command,
+        registry: &Registry,
+        tokens: StdioTokens,
+        trigger: Trigger,
+    ) -> io::Result<Child> {
+        let mut child = Child::spawn(command)?;
+        match child.register_stdio(registry, tokens, trigger) {
+            Ok(()) => Ok(child),
+            Err(e) => {
+                let _ = child.inner.kill();
+                let _ = child.inner.wait();
+                Err(e)
+            }
+        }
+    }
+
+    fn register_stdio(
+        &mut self,
+        registry: &Registry,
+        tokens: StdioTokens,
+        trigger: Trigger,
+    ) -> io::Result<()> {
+        if let Some(stdin) = &mut self.stdin {
+            registry.register(stdin, tokens.stdin, Interests::WRITABLE, trigger)?;
+        }
+        if let Some(stdout) = &mut self.stdout {
+            registry.register(stdout, tokens.stdout, Interests::READABLE, trigger)?;
+        }
+        if let Some(stderr) = &mut self.stderr {
+            registry.register(stderr, tokens.stderr, Interests::READABLE, trigger)?;
+        }
+        Ok(())
+    }
+
+    /// Takes over a child spawned with the standard library. Only children that haven't been
+    /// waited for yet can be used since the process id might be reused after that.
+    pub fn from_std(mut child: process::Child) -> io::Result<Child> {
+        let pidfd = pidfd_open(child.id())?;
+        let pidfd = unsafe { File::from_raw_fd(pidfd) };
+
+        // The standard library creates the pipes close-on-exec but blocking
+        let stdin = match child.stdin.take() {
+            Some(stdin) => Some(Sender::from_blocking(File::from(OwnedFd::from(stdin)))?),
+            None => None,
+        };
+        let stdout = match child.stdout.take() {
+            Some(stdout) => Some(Receiver::from_blocking(File::from(OwnedFd::from(stdout)))?),
+            None => None,
+        };
+        let stderr = match child.stderr.take() {
+            Some(stderr) => Some(Receiver::from_blocking(File::from(OwnedFd::from(stderr)))?),
+            None => None,
+        };
+
+        Ok(Child {
+            inner: child,
+            pidfd,
+            registration: None,
+            stdin,
+            stdout,
+            stderr,
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Returns the exit status if the child has exited, without blocking. Returns `Ok(None)`
+    /// if it's still running, in which case a `Trigger::Oneshot` registration is re-armed.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        let status = self.inner.try_wait()?;
+        if status.is_none() {
+            if let Some(registration) = &self.registration {
+                registration.rearm(self.pidfd.as_raw_fd())?;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Sends `SIGKILL` to the child. The `pidfd` becomes readable once it's gone.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.inner.kill()
+    }
+}
+
+impl AsRawFd for Child {
+    fn as_raw_fd(&self) -> RawFd {
+        self.pidfd.as_raw_fd()
+    }
+}
+
+impl Source for Child {
+    fn raw_fd(&self) -> RawFd {
+        self.pidfd.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+/// The tokens `Child::spawn_registered` registers the pipes of the standard streams with.
+/// The token of a stream that isn't piped is not used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StdioTokens {
+    pub stdin: Token,
+    pub stdout: Token,
+    pub stderr: Token,
+}
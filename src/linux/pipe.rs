@@ -0,0 +1,132 @@
+use super::{ffi, pipe2, rearm_on_would_block, set_nonblocking, Registration, Source};
+use std::fs::File;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+/// Creates an anonymous pipe. Both ends are non-blocking and closed on `exec`. Register the
+/// `Sender` with `Interests::WRITABLE` and the `Receiver` with `Interests::READABLE`.
+///
+/// When all `Sender`s are dropped the `Receiver` gets a readable event and `read` returns `0`.
+/// When the `Receiver` is dropped writing fails with an error of kind `BrokenPipe`.
+pub fn pipe() -> io::Result<(Sender, Receiver)> {
+    let (read, write) = pipe2(ffi::O_NONBLOCK | ffi::O_CLOEXEC)?;
+    let (read, write) = unsafe { (File::from_raw_fd(read), File::from_raw_fd(write)) };
+
+    Ok((Sender::from_file(write), Receiver::from_file(read)))
+}
+
+/// The write end of a pipe created with `pipe`. Writing returns an error of kind
+/// `WouldBlock` when the pipe is full, wait for the next writable event and try again.
+#[derive(Debug)]
+pub struct Sender {
+    inner: File,
+    registration: Option<Registration>,
+}
+
+impl Sender {
+    fn from_file(inner: File) -> Self {
+        Sender {
+            inner,
+            registration: None,
+        }
+    }
+
+    /// Takes over the write end of a pipe from somewhere else, like a child process' stdin,
+    /// and makes it non-blocking.
+    pub(super) fn from_blocking(inner: File) -> io::Result<Self> {
+        set_nonblocking(inner.as_raw_fd())?;
+        Ok(Sender::from_file(inner))
+    }
+}
+
+impl Write for Sender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let res = self.inner.write(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        let res = self.inner.write_vectored(bufs);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for Sender {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for Sender {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+/// The read end of a pipe created with `pipe`. Reading returns an error of kind `WouldBlock`
+/// when the pipe is empty, wait for the next readable event and read again.
+#[derive(Debug)]
+pub struct Receiver {
+    inner: File,
+    registration: Option<Registration>,
+}
+
+impl Receiver {
+    fn from_file(inner: File) -> Self {
+        Receiver {
+            inner,
+            registration: None,
+        }
+    }
+
+    /// Takes over the read end of a pipe from somewhere else, like a child process' stdout,
+    /// and makes it non-blocking.
+    pub(super) fn from_blocking(inner: File) -> io::Result<Self> {
+        set_nonblocking(inner.as_raw_fd())?;
+        Ok(Receiver::from_file(inner))
+    }
+}
+
+impl Read for Receiver {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.inner.read(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        let res = self.inner.read_vectored(bufs);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+}
+
+impl AsRawFd for Receiver {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for Receiver {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
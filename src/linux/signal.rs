@@ -0,0 +1,134 @@
+use super::{ffi, pthread_sigmask, rearm_on_would_block, signalfd, Registration, Source};
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+/// Receives signals through a `signalfd` so they can be handled in the event loop like any
+/// other source. Register it with `Interests::READABLE` and call `receive` until it returns
+/// `WouldBlock` after each readable event.
+///
+/// The signals are blocked so they aren't delivered the normal way. Signal masks are per
+/// thread and inherited by new threads, so create `Signals` on the main thread before
+/// spawning any others. A signal that arrives at a thread that doesn't block it gets the
+/// default handling (which for most signals ends the process). The signals stay blocked when
+/// `Signals` is dropped since unblocking would deliver any pending ones right away.
+#[derive(Debug)]
+pub struct Signals {
+    fd: File,
+    registration: Option<Registration>,
+}
+
+impl Signals {
+    pub const SIGHUP: i32 = 1;
+    pub const SIGINT: i32 = 2;
+    pub const SIGQUIT: i32 = 3;
+    pub const SIGUSR1: i32 = 10;
+    pub const SIGUSR2: i32 = 12;
+    pub const SIGPIPE: i32 = 13;
+    pub const SIGALRM: i32 = 14;
+    pub const SIGTERM: i32 = 15;
+    pub const SIGCHLD: i32 = 17;
+    pub const SIGWINCH: i32 = 28;
+
+    /// Blocks `signals` on the calling thread and creates a `signalfd` receiving them.
+    /// `SIGKILL` and `SIGSTOP` can't be caught and are silently ignored by the kernel.
+    pub fn new(signals: &[i32]) -> io::Result<Signals> {
+        let mut mask = ffi::Sigset::default();
+        for &signal in signals {
+            if !(1..=64).contains(&signal) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Invalid signal number.",
+                ));
+            }
+            mask.val[0] |= 1 << (signal - 1);
+        }
+
+        pthread_sigmask(ffi::SIG_BLOCK, &mask)?;
+        let fd = signalfd(&mask, ffi::SFD_NONBLOCK | ffi::SFD_CLOEXEC)?;
+        let fd = unsafe { File::from_raw_fd(fd) };
+
+        Ok(Signals {
+            fd,
+            registration: None,
+        })
+    }
+
+    /// Returns the next pending signal. Returns an error of kind `WouldBlock` if there are
+    /// none, in which case you should wait for the next readable event.
+    ///
+    /// Standard signals that arrive while one of the same kind is pending are merged into one.
+    pub fn receive(&mut self) -> io::Result<SignalInfo> {
+        let mut buf = [0; mem::size_of::<ffi::SignalfdSiginfo>()];
+        let res = (&self.fd).read(&mut buf).map(|_| {
+            // The kernel always hands out whole records, and `buf` fits exactly one
+            let info: ffi::SignalfdSiginfo = unsafe { mem::transmute(buf) };
+            SignalInfo {
+                signal: info.ssi_signo as i32,
+                code: info.ssi_code,
+                pid: info.ssi_pid,
+                uid: info.ssi_uid,
+                status: info.ssi_status,
+            }
+        });
+        rearm_on_would_block(&self.registration, self.fd.as_raw_fd(), res)
+    }
+}
+
+impl AsRawFd for Signals {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl Source for Signals {
+    fn raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+/// A signal received with `Signals::receive`, decoded from the kernel's `siginfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalInfo {
+    signal: i32,
+    code: i32,
+    pid: u32,
+    uid: u32,
+    status: i32,
+}
+
+impl SignalInfo {
+    pub fn signal(&self) -> i32 {
+        self.signal
+    }
+
+    /// Tells where the signal came from, e.g. `SI_USER` (0) for `kill` or `SI_TKILL` (-6) for
+    /// `tgkill`. For `SIGCHLD` it's one of the `CLD_*` codes instead.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// The process that sent the signal. For `SIGCHLD` it's the child that changed state.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// The real user id of the process that sent the signal.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// For `SIGCHLD` the exit status or the signal that changed the child's state.
+    pub fn status(&self) -> i32 {
+        self.status
+    }
+}
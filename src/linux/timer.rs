@@ -0,0 +1,162 @@
+use super::{clock_gettime, ffi, timerfd_create, timerfd_settime, Registration, Source};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::time::Duration;
+
+/// The clock a `Timer` measures time with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    /// Time since some unspecified point in the past (usually boot), never jumps. Use this
+    /// for timeouts and intervals.
+    Monotonic,
+    /// Wall clock time since the Unix epoch. It can be set by the user or NTP, so only use
+    /// it for deadlines that are tied to a date and time.
+    Realtime,
+}
+
+impl Clock {
+    fn id(self) -> i32 {
+        match self {
+            Clock::Monotonic => ffi::CLOCK_MONOTONIC,
+            Clock::Realtime => ffi::CLOCK_REALTIME,
+        }
+    }
+}
+
+/// A timer backed by a `timerfd`. Register it with `Interests::READABLE` and you get a
+/// readable event when it expires. Call `expirations` after the event to find out how many
+/// times it expired since the last read, which is more than one for a periodic timer if the
+/// event loop was slow to get around to it.
+///
+/// A new timer is disarmed, use one of the `set_*` methods to start it. Setting a timer
+/// replaces whatever it was set to before.
+#[derive(Debug)]
+pub struct Timer {
+    fd: File,
+    clock: Clock,
+    registration: Option<Registration>,
+}
+
+impl Timer {
+    pub fn new(clock: Clock) -> io::Result<Timer> {
+        let fd = timerfd_create(clock.id(), ffi::TFD_NONBLOCK | ffi::TFD_CLOEXEC)?;
+        let fd = unsafe { File::from_raw_fd(fd) };
+
+        Ok(Timer {
+            fd,
+            clock,
+            registration: None,
+        })
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+
+    /// The current time on the timer's clock, which is what `set_deadline` expects.
+    pub fn now(&self) -> io::Result<Duration> {
+        let now = clock_gettime(self.clock.id())?;
+        Ok(Duration::new(now.tv_sec as u64, now.tv_nsec as u32))
+    }
+
+    /// Expires once after `delay`.
+    pub fn set_timeout(&self, delay: Duration) -> io::Result<()> {
+        self.set(0, delay, Duration::from_secs(0))
+    }
+
+    /// Expires every `interval`, starting `interval` from now.
+    pub fn set_interval(&self, interval: Duration) -> io::Result<()> {
+        self.set(0, interval, interval)
+    }
+
+    /// Expires when the timer's clock reaches `deadline` (see `now`) and then every `interval`
+    /// if it's `Some`. A deadline in the past expires right away.
+    ///
+    /// If the clock is `Clock::Realtime` and someone sets the system time, the timer is
+    /// cancelled and `expirations` returns an error. Set it again to resume.
+    pub fn set_deadline(&self, deadline: Duration, interval: Option<Duration>) -> io::Result<()> {
+        let mut flags = ffi::TFD_TIMER_ABSTIME;
+        if self.clock == Clock::Realtime {
+            flags |= ffi::TFD_TIMER_CANCEL_ON_SET;
+        }
+
+        self.set(flags, deadline, interval.unwrap_or_default())
+    }
+
+    /// Stops the timer. Expirations that haven't been read are discarded.
+    pub fn disarm(&self) -> io::Result<()> {
+        timerfd_settime(self.fd.as_raw_fd(), 0, &ffi::Itimerspec::default())
+    }
+
+    /// Returns how many times the timer expired since it was set or last read. Returns an error
+    /// of kind `WouldBlock` if it hasn't expired, in which case you should wait for the next
+    /// readable event.
+    pub fn expirations(&self) -> io::Result<u64> {
+        let mut buf = [0; 8];
+        match (&self.fd).read(&mut buf) {
+            // A successful read resets the count, so there's nothing left to read and we can
+            // re-arm right away instead of waiting for a `WouldBlock` nobody asks for
+            Ok(_) => {
+                self.rearm()?;
+                Ok(u64::from_ne_bytes(buf))
+            }
+            Err(ref e) if e.raw_os_error() == Some(ffi::ECANCELED) => Err(io::Error::other(
+                "Timer was cancelled since the clock was set.",
+            )),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    self.rearm()?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn set(&self, flags: i32, value: Duration, interval: Duration) -> io::Result<()> {
+        // A zero value disarms the timer, so the shortest timeout we can ask for is 1 nanosecond
+        let value = value.max(Duration::from_nanos(1));
+        let spec = ffi::Itimerspec {
+            it_interval: to_timespec(interval),
+            it_value: to_timespec(value),
+        };
+        timerfd_settime(self.fd.as_raw_fd(), flags, &spec)?;
+        // A `Trigger::Oneshot` registration stays disarmed after the timer fired, so setting it
+        // again would go unnoticed unless we re-arm it too
+        self.rearm()
+    }
+
+    fn rearm(&self) -> io::Result<()> {
+        if let Some(registration) = &self.registration {
+            registration.rearm(self.fd.as_raw_fd())?;
+        }
+        Ok(())
+    }
+}
+
+fn to_timespec(duration: Duration) -> ffi::Timespec {
+    ffi::Timespec {
+        tv_sec: duration.as_secs() as i64,
+        tv_nsec: duration.subsec_nanos() as i64,
+    }
+}
+
+impl AsRawFd for Timer {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl Source for Timer {
+    fn raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
@@ -0,0 +1,130 @@
+use super::{rearm_on_would_block, Registration, Source};
+use std::io;
+use std::net::{self, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// A non-blocking UDP socket. Register it with `Interests::READABLE` to get notified when
+/// there are datagrams to receive, and `Interests::WRITABLE` when there is room to send.
+///
+/// Receiving returns an error of kind `WouldBlock` when there are no datagrams waiting, and
+/// sending when the send buffer is full. Either re-arms a `Trigger::Oneshot` registration just
+/// like reading from or writing to a `TcpStream` does.
+#[derive(Debug)]
+pub struct UdpSocket {
+    inner: net::UdpSocket,
+    registration: Option<Registration>,
+}
+
+impl UdpSocket {
+    pub fn bind(adr: impl net::ToSocketAddrs) -> io::Result<Self> {
+        let socket = net::UdpSocket::bind(adr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(UdpSocket {
+            inner: socket,
+            registration: None,
+        })
+    }
+
+    /// Sets the default address for `send` and limits `recv` to datagrams from that address.
+    /// Connecting a UDP socket doesn't send anything so this never blocks.
+    pub fn connect(&self, adr: impl net::ToSocketAddrs) -> io::Result<()> {
+        self.inner.connect(adr)
+    }
+
+    pub fn send_to(&self, buf: &[u8], target: impl net::ToSocketAddrs) -> io::Result<usize> {
+        let res = self.inner.send_to(buf, target);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let res = self.inner.recv_from(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    /// Like `recv_from` but the datagram stays in the queue.
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let res = self.inner.peek_from(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let res = self.inner.send(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.inner.recv(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.inner.peek(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+        self.inner.set_broadcast(broadcast)
+    }
+
+    pub fn broadcast(&self) -> io::Result<bool> {
+        self.inner.broadcast()
+    }
+
+    pub fn join_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        self.inner.join_multicast_v4(multiaddr, interface)
+    }
+
+    pub fn leave_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        self.inner.leave_multicast_v4(multiaddr, interface)
+    }
+
+    /// `interface` is the index of the network interface, `0` lets the OS choose.
+    pub fn join_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.inner.join_multicast_v6(multiaddr, interface)
+    }
+
+    pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.inner.leave_multicast_v6(multiaddr, interface)
+    }
+
+    pub fn set_multicast_loop_v4(&self, multicast_loop: bool) -> io::Result<()> {
+        self.inner.set_multicast_loop_v4(multicast_loop)
+    }
+
+    pub fn set_multicast_ttl_v4(&self, ttl: u32) -> io::Result<()> {
+        self.inner.set_multicast_ttl_v4(ttl)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+}
+
+impl AsRawFd for UdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for UdpSocket {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
@@ -0,0 +1,407 @@
+use super::{
+    accept4, bind, connect, ffi, listen, rearm_on_would_block, socket, socketpair, to_sockaddr_un,
+    Registration, Source,
+};
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::{self, SocketAddr};
+use std::path::Path;
+
+/// A non-blocking Unix stream socket. Works the same way as a `TcpStream`.
+///
+/// Addresses in the Linux abstract namespace are created with
+/// `std::os::linux::net::SocketAddrExt::from_abstract_name` and passed to the `*_addr` methods.
+#[derive(Debug)]
+pub struct UnixStream {
+    inner: net::UnixStream,
+    registration: Option<Registration>,
+}
+
+impl UnixStream {
+    pub fn connect(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::connect_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    /// Connecting a Unix socket doesn't involve a handshake so it either succeeds or fails right
+    /// away. If the listener's backlog is full you get an error of kind `WouldBlock` instead of
+    /// waiting for room.
+    pub fn connect_addr(adr: &SocketAddr) -> io::Result<Self> {
+        let fd = socket(
+            ffi::AF_UNIX,
+            ffi::SOCK_STREAM | ffi::SOCK_NONBLOCK | ffi::SOCK_CLOEXEC,
+        )?;
+        let stream = unsafe { net::UnixStream::from_raw_fd(fd) };
+
+        let (adr, len) = to_sockaddr_un(adr)?;
+        connect(fd, &adr, len)?;
+
+        Ok(UnixStream {
+            inner: stream,
+            registration: None,
+        })
+    }
+
+    /// Creates a pair of connected sockets.
+    pub fn pair() -> io::Result<(UnixStream, UnixStream)> {
+        let (a, b) = net::UnixStream::pair()?;
+        a.set_nonblocking(true)?;
+        b.set_nonblocking(true)?;
+
+        Ok((UnixStream::from_std(a), UnixStream::from_std(b)))
+    }
+
+    fn from_std(stream: net::UnixStream) -> Self {
+        UnixStream {
+            inner: stream,
+            registration: None,
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+}
+
+impl Read for UnixStream {
+    /// Returns an error of kind `WouldBlock` when there is no more data to read right now.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = (&self.inner).read(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        let res = (&self.inner).read_vectored(bufs);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+}
+
+impl Write for UnixStream {
+    /// Returns an error of kind `WouldBlock` when the send buffer is full.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let res = (&self.inner).write(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        let res = (&self.inner).write_vectored(bufs);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl AsRawFd for UnixStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for UnixStream {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+/// A non-blocking Unix socket listening for connections. Register it with
+/// `Interests::READABLE` and a readable event means there are connections waiting.
+#[derive(Debug)]
+pub struct UnixListener {
+    inner: net::UnixListener,
+    registration: Option<Registration>,
+}
+
+impl UnixListener {
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::bind_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    pub fn bind_addr(adr: &SocketAddr) -> io::Result<Self> {
+        let listener = net::UnixListener::bind_addr(adr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(UnixListener {
+            inner: listener,
+            registration: None,
+        })
+    }
+
+    /// Accepts a pending connection. Returns an error of kind `WouldBlock` if there are no
+    /// connections waiting, in which case you should wait for the next readable event.
+    pub fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
+        let res = accept_nonblocking(self.inner.as_raw_fd());
+        let fd = rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)?;
+        let stream = unsafe { net::UnixStream::from_raw_fd(fd) };
+        let adr = stream.peer_addr()?;
+
+        Ok((UnixStream::from_std(stream), adr))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+impl AsRawFd for UnixListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for UnixListener {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+/// A non-blocking `SOCK_SEQPACKET` listener. Connections keep the message boundaries like a
+/// datagram socket but are reliable and ordered like a stream, so they're handed out as
+/// `UnixDatagram`s. Register it with `Interests::READABLE` and a readable event means there are
+/// connections waiting.
+#[derive(Debug)]
+pub struct UnixSeqpacketListener {
+    // Only used to close the socket and look up its address. Its `accept` would hand out
+    // stream sockets, so we never call it.
+    inner: net::UnixListener,
+    registration: Option<Registration>,
+}
+
+impl UnixSeqpacketListener {
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::bind_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    pub fn bind_addr(adr: &SocketAddr) -> io::Result<Self> {
+        let fd = socket(
+            ffi::AF_UNIX,
+            ffi::SOCK_SEQPACKET | ffi::SOCK_NONBLOCK | ffi::SOCK_CLOEXEC,
+        )?;
+        let listener = unsafe { net::UnixListener::from_raw_fd(fd) };
+
+        let (adr, len) = to_sockaddr_un(adr)?;
+        bind(fd, &adr, len)?;
+        // Same backlog as the standard library uses
+        listen(fd, 128)?;
+
+        Ok(UnixSeqpacketListener {
+            inner: listener,
+            registration: None,
+        })
+    }
+
+    /// Accepts a pending connection. Returns an error of kind `WouldBlock` if there are no
+    /// connections waiting, in which case you should wait for the next readable event.
+    pub fn accept(&self) -> io::Result<(UnixDatagram, SocketAddr)> {
+        let res = accept_nonblocking(self.inner.as_raw_fd());
+        let fd = rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)?;
+        let socket = unsafe { net::UnixDatagram::from_raw_fd(fd) };
+        let adr = socket.peer_addr()?;
+
+        Ok((UnixDatagram::from_std(socket), adr))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+impl AsRawFd for UnixSeqpacketListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for UnixSeqpacketListener {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+fn accept_nonblocking(fd: RawFd) -> io::Result<RawFd> {
+    accept4(fd, ffi::SOCK_NONBLOCK | ffi::SOCK_CLOEXEC)
+}
+
+/// A non-blocking Unix datagram socket, either `SOCK_DGRAM` or, when created with
+/// `seqpacket_pair`, `connect_seqpacket` or `UnixSeqpacketListener::accept`, a connected
+/// `SOCK_SEQPACKET` socket. Either way every `send` is received by exactly one `recv`.
+#[derive(Debug)]
+pub struct UnixDatagram {
+    inner: net::UnixDatagram,
+    registration: Option<Registration>,
+}
+
+impl UnixDatagram {
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::bind_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    pub fn bind_addr(adr: &SocketAddr) -> io::Result<Self> {
+        let socket = net::UnixDatagram::bind_addr(adr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(UnixDatagram::from_std(socket))
+    }
+
+    /// Creates a socket that isn't bound to any address.
+    pub fn unbound() -> io::Result<Self> {
+        let socket = net::UnixDatagram::unbound()?;
+        socket.set_nonblocking(true)?;
+
+        Ok(UnixDatagram::from_std(socket))
+    }
+
+    /// Creates a pair of connected `SOCK_DGRAM` sockets.
+    pub fn pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
+        let (a, b) = net::UnixDatagram::pair()?;
+        a.set_nonblocking(true)?;
+        b.set_nonblocking(true)?;
+
+        Ok((UnixDatagram::from_std(a), UnixDatagram::from_std(b)))
+    }
+
+    /// Creates a pair of connected `SOCK_SEQPACKET` sockets.
+    pub fn seqpacket_pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
+        let (a, b) = socketpair(
+            ffi::AF_UNIX,
+            ffi::SOCK_SEQPACKET | ffi::SOCK_NONBLOCK | ffi::SOCK_CLOEXEC,
+        )?;
+        let (a, b) = unsafe {
+            (
+                net::UnixDatagram::from_raw_fd(a),
+                net::UnixDatagram::from_raw_fd(b),
+            )
+        };
+
+        Ok((UnixDatagram::from_std(a), UnixDatagram::from_std(b)))
+    }
+
+    /// Connects a `SOCK_SEQPACKET` socket to a `UnixSeqpacketListener`. Like
+    /// `UnixStream::connect_addr` this doesn't wait.
+    pub fn connect_seqpacket(adr: &SocketAddr) -> io::Result<Self> {
+        let fd = socket(
+            ffi::AF_UNIX,
+            ffi::SOCK_SEQPACKET | ffi::SOCK_NONBLOCK | ffi::SOCK_CLOEXEC,
+        )?;
+        let socket = unsafe { net::UnixDatagram::from_raw_fd(fd) };
+
+        let (adr, len) = to_sockaddr_un(adr)?;
+        connect(fd, &adr, len)?;
+
+        Ok(UnixDatagram::from_std(socket))
+    }
+
+    fn from_std(socket: net::UnixDatagram) -> Self {
+        UnixDatagram {
+            inner: socket,
+            registration: None,
+        }
+    }
+
+    /// Sets the default address for `send` and limits `recv` to datagrams from that address.
+    pub fn connect(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.inner.connect(path)
+    }
+
+    pub fn connect_addr(&self, adr: &SocketAddr) -> io::Result<()> {
+        self.inner.connect_addr(adr)
+    }
+
+    pub fn send_to(&self, buf: &[u8], path: impl AsRef<Path>) -> io::Result<usize> {
+        let res = self.inner.send_to(buf, path);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn send_to_addr(&self, buf: &[u8], adr: &SocketAddr) -> io::Result<usize> {
+        let res = self.inner.send_to_addr(buf, adr);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let res = self.inner.recv_from(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let res = self.inner.send(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.inner.recv(buf);
+        rearm_on_would_block(&self.registration, self.inner.as_raw_fd(), res)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+}
+
+impl AsRawFd for UnixDatagram {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl Source for UnixDatagram {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
@@ -0,0 +1,350 @@
+use super::{
+    ffi, inotify_add_watch, inotify_init1, inotify_rm_watch, rearm_on_would_block, Registration,
+    Source,
+};
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::{fmt, ops};
+
+/// The size of `struct inotify_event` without the name that follows it.
+const EVENT_SIZE: usize = 16;
+
+/// Watches files and directories for changes using `inotify`. Register it with
+/// `Interests::READABLE` and call `read_events` until it returns `WouldBlock` after each
+/// readable event.
+///
+/// Watching a directory reports changes to the files in it (not recursively), with the name
+/// of the file in the event. Watching a file reports changes to the file itself.
+#[derive(Debug)]
+pub struct Watcher {
+    fd: File,
+    registration: Option<Registration>,
+    buffer: Vec<u8>,
+}
+
+impl Watcher {
+    pub fn new() -> io::Result<Watcher> {
+        let fd = inotify_init1(ffi::IN_NONBLOCK | ffi::IN_CLOEXEC)?;
+        let fd = unsafe { File::from_raw_fd(fd) };
+
+        Ok(Watcher {
+            fd,
+            registration: None,
+            // Room for plenty of events, and always for at least one with the longest name
+            buffer: vec![0; 16 * 1024],
+        })
+    }
+
+    /// Starts watching `path`. Adding a path that is already watched replaces its mask and
+    /// returns the same descriptor.
+    pub fn add_watch(
+        &self,
+        path: impl AsRef<Path>,
+        mask: WatchMask,
+    ) -> io::Result<WatchDescriptor> {
+        inotify_add_watch(self.fd.as_raw_fd(), path.as_ref(), mask.0).map(WatchDescriptor)
+    }
+
+    /// Stops watching. A `WatchEventKind::Removed` event is delivered for the descriptor
+    /// after this, and after the watched path is deleted or unmounted.
+    pub fn remove_watch(&self, wd: WatchDescriptor) -> io::Result<()> {
+        inotify_rm_watch(self.fd.as_raw_fd(), wd.0)
+    }
+
+    /// Reads the events that are ready. Returns an error of kind `WouldBlock` if there are
+    /// none, in which case you should wait for the next readable event.
+    ///
+    /// A `IN_MOVED_FROM` record and the `IN_MOVED_TO` with the same cookie that follows it in
+    /// the same read are turned into a single `WatchEventKind::Moved`, in the place of the
+    /// `MovedFrom`, even if there are other records in between. If only one end of the move is
+    /// watched, or they end up in different reads, you get a `MovedFrom` or `MovedTo` with the
+    /// cookie instead.
+    pub fn read_events(&mut self) -> io::Result<Vec<WatchEvent>> {
+        let res = (&self.fd).read(&mut self.buffer);
+        let n = rearm_on_would_block(&self.registration, self.fd.as_raw_fd(), res)?;
+
+        Ok(parse_events(&self.buffer[..n]))
+    }
+}
+
+/// Parses the `struct inotify_event` records the kernel wrote to `buf`. Records we don't know
+/// the kind of are skipped.
+fn parse_events(buf: &[u8]) -> Vec<WatchEvent> {
+    let mut events: Vec<WatchEvent> = vec![];
+    let mut pos = 0;
+    while pos + EVENT_SIZE <= buf.len() {
+        let field = |i: usize| {
+            let start = pos + i * 4;
+            u32::from_ne_bytes([buf[start], buf[start + 1], buf[start + 2], buf[start + 3]])
+        };
+        let (wd, mask, cookie, len) = (field(0) as i32, field(1), field(2), field(3) as usize);
+
+        // The name is padded with nul bytes
+        let name = &buf[pos + EVENT_SIZE..pos + EVENT_SIZE + len];
+        let name = match name.iter().position(|&b| b == 0).unwrap_or(len) {
+            0 => None,
+            end => Some(PathBuf::from(OsStr::from_bytes(&name[..end]))),
+        };
+        pos += EVENT_SIZE + len;
+
+        let wd = WatchDescriptor(wd);
+        let is_dir = mask & ffi::IN_ISDIR != 0;
+
+        if mask & ffi::IN_MOVED_TO != 0 {
+            // Cookies are unique per move, so records of other changes in between don't matter
+            let from = events.iter_mut().rev().find(|event| match event.kind {
+                WatchEventKind::MovedFrom { cookie: from } => from == cookie,
+                _ => false,
+            });
+            if let Some(from) = from {
+                from.kind = WatchEventKind::Moved {
+                    from_wd: from.wd,
+                    from_name: from.name.take(),
+                };
+                from.wd = wd;
+                from.name = name;
+                continue;
+            }
+        }
+
+        if let Some(kind) = WatchEventKind::from_mask(mask, cookie) {
+            events.push(WatchEvent {
+                wd,
+                name,
+                is_dir,
+                kind,
+            });
+        }
+    }
+
+    events
+}
+
+impl AsRawFd for Watcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl Source for Watcher {
+    fn raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+/// Identifies a watch added with `Watcher::add_watch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchDescriptor(i32);
+
+/// The changes a watch reports. Combine them with `|`:
+///
+/// - WatchMask::CREATE: a file was created in a watched directory
+/// - WatchMask::MODIFY: a file was written to
+/// - WatchMask::ATTRIB: permissions, timestamps, owner etc. changed
+/// - WatchMask::CLOSE_WRITE: a file opened for writing was closed
+/// - WatchMask::DELETE: a file was deleted from a watched directory
+/// - WatchMask::MOVE: a file was moved into, out of or within a watched directory
+/// - WatchMask::DELETE_SELF / WatchMask::MOVE_SELF: the watched path itself was deleted or moved
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WatchMask(u32);
+impl WatchMask {
+    pub const CREATE: WatchMask = WatchMask(ffi::IN_CREATE);
+    pub const MODIFY: WatchMask = WatchMask(ffi::IN_MODIFY);
+    pub const ATTRIB: WatchMask = WatchMask(ffi::IN_ATTRIB);
+    pub const CLOSE_WRITE: WatchMask = WatchMask(ffi::IN_CLOSE_WRITE);
+    pub const DELETE: WatchMask = WatchMask(ffi::IN_DELETE);
+    pub const MOVED_FROM: WatchMask = WatchMask(ffi::IN_MOVED_FROM);
+    pub const MOVED_TO: WatchMask = WatchMask(ffi::IN_MOVED_TO);
+    pub const MOVE: WatchMask = WatchMask(ffi::IN_MOVED_FROM | ffi::IN_MOVED_TO);
+    pub const DELETE_SELF: WatchMask = WatchMask(ffi::IN_DELETE_SELF);
+    pub const MOVE_SELF: WatchMask = WatchMask(ffi::IN_MOVE_SELF);
+
+    /// Same as `self | other` but usable in a `const` context.
+    pub const fn add(self, other: WatchMask) -> WatchMask {
+        WatchMask(self.0 | other.0)
+    }
+}
+
+impl ops::BitOr for WatchMask {
+    type Output = WatchMask;
+
+    fn bitor(self, other: WatchMask) -> WatchMask {
+        self.add(other)
+    }
+}
+
+impl ops::BitOrAssign for WatchMask {
+    fn bitor_assign(&mut self, other: WatchMask) {
+        *self = self.add(other);
+    }
+}
+
+impl fmt::Debug for WatchMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WatchMask({:#x})", self.0)
+    }
+}
+
+/// A change reported by a `Watcher`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    /// The watch that reported the change. For a `Moved` event it's the destination.
+    pub wd: WatchDescriptor,
+    /// The name of the file in a watched directory, `None` if the event is about the
+    /// watched path itself.
+    pub name: Option<PathBuf>,
+    pub is_dir: bool,
+    pub kind: WatchEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEventKind {
+    Created,
+    Modified,
+    AttributesChanged,
+    ClosedWrite,
+    Deleted,
+    /// Moved within or between watched directories. The event's `wd` and `name` is where it
+    /// was moved to.
+    Moved {
+        from_wd: WatchDescriptor,
+        from_name: Option<PathBuf>,
+    },
+    /// Moved away from a watched directory. A `MovedTo` with the same cookie is the other
+    /// end of the move if it ended up in a watched directory.
+    MovedFrom {
+        cookie: u32,
+    },
+    /// Moved into a watched directory.
+    MovedTo {
+        cookie: u32,
+    },
+    DeletedSelf,
+    MovedSelf,
+    /// The file system the watched path is on was unmounted. Always reported, even if not
+    /// asked for, and followed by `Removed`.
+    Unmounted,
+    /// The watch was removed, either by `remove_watch` or because the path is gone.
+    Removed,
+    /// The kernel's event queue overflowed and events were lost.
+    Overflow,
+}
+
+impl WatchEventKind {
+    fn from_mask(mask: u32, cookie: u32) -> Option<Self> {
+        let kind = if mask & ffi::IN_Q_OVERFLOW != 0 {
+            WatchEventKind::Overflow
+        } else if mask & ffi::IN_IGNORED != 0 {
+            WatchEventKind::Removed
+        } else if mask & ffi::IN_UNMOUNT != 0 {
+            WatchEventKind::Unmounted
+        } else if mask & ffi::IN_CREATE != 0 {
+            WatchEventKind::Created
+        } else if mask & ffi::IN_MODIFY != 0 {
+            WatchEventKind::Modified
+        } else if mask & ffi::IN_ATTRIB != 0 {
+            WatchEventKind::AttributesChanged
+        } else if mask & ffi::IN_CLOSE_WRITE != 0 {
+            WatchEventKind::ClosedWrite
+        } else if mask & ffi::IN_DELETE != 0 {
+            WatchEventKind::Deleted
+        } else if mask & ffi::IN_MOVED_FROM != 0 {
+            WatchEventKind::MovedFrom { cookie }
+        } else if mask & ffi::IN_MOVED_TO != 0 {
+            WatchEventKind::MovedTo { cookie }
+        } else if mask & ffi::IN_DELETE_SELF != 0 {
+            WatchEventKind::DeletedSelf
+        } else if mask & ffi::IN_MOVE_SELF != 0 {
+            WatchEventKind::MovedSelf
+        } else {
+            return None;
+        };
+
+        Some(kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends a record the way the kernel lays it out, with the name padded to 4 bytes.
+    fn record(buf: &mut Vec<u8>, wd: i32, mask: u32, cookie: u32, name: &str) {
+        let len = if name.is_empty() {
+            0
+        } else {
+            (name.len() + 4) & !3
+        };
+        for field in [wd as u32, mask, cookie, len as u32].iter() {
+            buf.extend_from_slice(&field.to_ne_bytes());
+        }
+        buf.extend_from_slice(name.as_bytes());
+        buf.resize(buf.len() + len - name.len(), 0);
+    }
+
+    #[test]
+    fn unmount_is_not_reported_as_moved_self() {
+        let mut buf = vec![];
+        record(&mut buf, 1, ffi::IN_UNMOUNT, 0, "");
+        record(&mut buf, 1, ffi::IN_IGNORED, 0, "");
+        record(&mut buf, 2, ffi::IN_MOVE_SELF, 0, "");
+
+        let kinds: Vec<_> = parse_events(&buf).into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                WatchEventKind::Unmounted,
+                WatchEventKind::Removed,
+                WatchEventKind::MovedSelf
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_records_are_skipped() {
+        let mut buf = vec![];
+        // `IN_ACCESS`, which we never ask for
+        record(&mut buf, 1, 0x1, 0, "file");
+        record(&mut buf, 1, ffi::IN_CREATE, 0, "file");
+
+        let events = parse_events(&buf);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, WatchEventKind::Created);
+        assert_eq!(events[0].name.as_deref(), Some(Path::new("file")));
+    }
+
+    #[test]
+    fn moves_are_merged_across_other_records() {
+        let mut buf = vec![];
+        record(&mut buf, 1, ffi::IN_MOVED_FROM, 7, "log");
+        record(&mut buf, 3, ffi::IN_MODIFY, 0, "other");
+        record(&mut buf, 1, ffi::IN_MOVED_FROM, 8, "dir");
+        record(&mut buf, 2, ffi::IN_MOVED_TO, 7, "log.1");
+
+        let events = parse_events(&buf);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].wd, WatchDescriptor(2));
+        assert_eq!(events[0].name.as_deref(), Some(Path::new("log.1")));
+        assert_eq!(
+            events[0].kind,
+            WatchEventKind::Moved {
+                from_wd: WatchDescriptor(1),
+                from_name: Some(PathBuf::from("log")),
+            }
+        );
+        assert_eq!(events[1].kind, WatchEventKind::Modified);
+        assert_eq!(events[2].kind, WatchEventKind::MovedFrom { cookie: 8 });
+    }
+}
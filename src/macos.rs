@@ -1,86 +1,126 @@
-use crate::{Events, Interests, Token};
+use crate::unix::Source;
+use crate::{Error, Events, Interests, LoopState, Token, Trigger, CLOSE_TOKEN};
 use std::io::{self, IoSliceMut, Read, Write};
 use std::net;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::ptr;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
-
-pub type Source = std::os::unix::io::RawFd;
+use std::sync::Arc;
 
+/// Registers sources with a `Poll` from any thread. It's cheap to clone, so every thread that
+/// needs one can have its own, and they can all be used at the same time. It keeps the kqueue
+/// open, but once the `Poll` is dropped every registration fails with `Error::Closed`.
+#[derive(Debug, Clone)]
 pub struct Registrator {
-    kq: Source,
-    is_poll_dead: Arc<AtomicBool>,
+    kq: Arc<SelectorFd>,
+    state: Arc<LoopState>,
 }
 
 impl Registrator {
-    pub fn register(
+    pub fn register<S: Source + ?Sized>(
         &self,
-        stream: &TcpStream,
-        token: usize,
+        source: &mut S,
+        token: Token,
         interests: Interests,
-    ) -> io::Result<()> {
-        if self.is_poll_dead.load(Ordering::SeqCst) {
-            return Err(io::Error::new(
-                io::ErrorKind::Interrupted,
-                "Poll instance closed.",
-            ));
-        }
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        kqueue_register(self.kq.0, source.raw_fd(), token, interests, trigger)
+    }
 
-        let fd = stream.as_raw_fd();
-        if interests.is_readable() {
-            // We register the id (or most oftenly referred to as a Token) to the `udata` field
-            // if the `Kevent`
-            let event = ffi::Event::new_read_event(fd, token as u64);
-            let event = [event];
-            kevent(self.kq, &event, &mut [], 0, None)?;
-        };
+    /// Changes the token, interests and trigger of an already registered source. This is
+    /// also how you re-arm a `Trigger::Oneshot` registration after it has reported an event.
+    pub fn reregister<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        kqueue_register(self.kq.0, source.raw_fd(), token, interests, trigger)
+    }
 
-        if interests.is_writable() {
-            unimplemented!();
+    pub fn deregister<S: Source + ?Sized>(&self, source: &mut S) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        kqueue_deregister(self.kq.0, source.raw_fd())
+    }
+
+    fn check_poll_alive(&self) -> Result<(), Error> {
+        if !self.state.is_open() {
+            return Err(Error::Closed);
         }
 
         Ok(())
     }
 
-    pub fn close_loop(&self) -> io::Result<()> {
-        // We set already here that the Poll instance is dead since this will be the last
-        // event it will handle
-        if self
-            .is_poll_dead
-            .compare_and_swap(false, true, Ordering::SeqCst)
-        {
-            return Err(io::Error::new(
-                io::ErrorKind::Interrupted,
-                "Poll instance closed.",
-            ));
+    /// Shuts down the event loop gracefully. The `Poll` wakes up and still returns the events
+    /// that were ready, then `Error::Closed` from every call after that.
+    ///
+    /// Returns `Error::Closed` if the loop is already shut down.
+    pub fn close_loop(&self) -> Result<(), Error> {
+        if !self.state.close() {
+            return Err(Error::Closed);
         }
-        let event = ffi::Event::new_wakeup_event();
+
+        self.wake_poll()
+    }
+
+    /// Shuts down the event loop right away. The `Poll` wakes up and returns `Error::Closed`,
+    /// events that were ready are dropped.
+    ///
+    /// Returns `Error::Closed` if the loop is already shut down.
+    pub fn abort_loop(&self) -> Result<(), Error> {
+        if !self.state.abort() {
+            return Err(Error::Closed);
+        }
+
+        self.wake_poll()
+    }
+
+    fn wake_poll(&self) -> Result<(), Error> {
+        let event = ffi::Event::new_wakeup_event(CLOSE_TOKEN as u64);
         let event = [event];
-        kevent(self.kq, &event, &mut [], 0, None)?;
+        kevent(self.kq.0, &event, ptr::null_mut(), 0, None)?;
 
         Ok(())
     }
 }
 
+/// The kqueue file descriptor, shared by the `Selector` and its `Registrator`s and closed when
+/// the last of them is dropped.
 #[derive(Debug)]
+struct SelectorFd(RawFd);
+
+impl Drop for SelectorFd {
+    fn drop(&mut self) {
+        match close(self.0) {
+            Ok(..) => (),
+            Err(e) => {
+                if !std::thread::panicking() {
+                    panic!("{}", e);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Selector {
-    kq: Source,
+    kq: Arc<SelectorFd>,
 }
 
 impl Selector {
     pub fn new() -> io::Result<Self> {
-        Ok(Selector { kq: kqueue()? })
+        Ok(Selector {
+            kq: Arc::new(SelectorFd(kqueue()?)),
+        })
     }
 
     /// This function blocks and waits until an event has been recieved. It never times out.
     pub fn select(&self, events: &mut Events, timeout_ms: Option<i32>) -> io::Result<()> {
-        // TODO: get n_events from self
         let n_events = events.capacity() as i32;
         events.clear();
-        kevent(self.kq, &[], events, n_events, timeout_ms).map(|n_events| {
+        kevent(self.kq.0, &[], events.as_mut_ptr(), n_events, timeout_ms).map(|n_events| {
             // This is safe because `syscall_kevent` ensures that `n_events` are
             // assigned. We could check for a valid token for each event to verify so this is
             // just a performance optimization used in `mio` and copied here.
@@ -88,32 +128,101 @@ impl Selector {
         })
     }
 
-    pub fn registrator(&self, is_poll_dead: Arc<AtomicBool>) -> Registrator {
+    pub(crate) fn registrator(&self, state: Arc<LoopState>) -> Registrator {
         Registrator {
-            kq: self.kq,
-            is_poll_dead,
+            kq: self.kq.clone(),
+            state,
         }
     }
+
+    pub fn register<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        kqueue_register(self.kq.0, source.raw_fd(), token, interests, trigger)
+    }
+
+    /// `EV_ADD` on an existing filter modifies it, so this is the same as `register`.
+    pub fn reregister<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Token,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        kqueue_register(self.kq.0, source.raw_fd(), token, interests, trigger)
+    }
+
+    pub fn deregister<S: Source + ?Sized>(&self, source: &mut S) -> Result<(), Error> {
+        kqueue_deregister(self.kq.0, source.raw_fd())
+    }
 }
 
-impl Drop for Selector {
-    fn drop(&mut self) {
-        match close(self.kq) {
-            Ok(..) => (),
-            Err(e) => {
-                if !std::thread::panicking() {
-                    panic!(e);
-                }
-            }
-        }
+fn kqueue_register(
+    kq: RawFd,
+    fd: RawFd,
+    token: Token,
+    interests: Interests,
+    trigger: Trigger,
+) -> Result<(), Error> {
+    let trigger_flags = match trigger {
+        Trigger::Oneshot => ffi::EV_ONESHOT,
+        Trigger::Edge => ffi::EV_CLEAR,
+        Trigger::Level => 0,
+    };
+
+    if interests.is_readable() {
+        // We register the id (or most oftenly referred to as a Token) to the `udata` field
+        // if the `Kevent`
+        let event = ffi::Event::new_read_event(fd, token as u64, trigger_flags);
+        let event = [event];
+        kevent(kq, &event, ptr::null_mut(), 0, None)?;
+    };
+
+    if interests.is_writable() {
+        unimplemented!();
+    }
+
+    Ok(())
+}
+
+/// Returns `Error::NotRegistered` (ENOENT) if the fd was never registered.
+fn kqueue_deregister(kq: RawFd, fd: RawFd) -> Result<(), Error> {
+    let event = ffi::Event::new_delete_read_event(fd);
+    let event = [event];
+    match kevent(kq, &event, ptr::null_mut(), 0, None) {
+        Ok(_) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Err(Error::NotRegistered),
+        Err(e) => Err(Error::Io(e)),
     }
 }
 
 pub type Event = ffi::Kevent;
 impl Event {
+    /// We never register `EVFILT_USER` so we use it to mark the events we make for expired
+    /// timeouts.
+    pub(crate) fn timeout(token: Token) -> Event {
+        Event {
+            ident: 0,
+            filter: ffi::EVFILT_USER,
+            flags: 0,
+            fflags: 0,
+            data: 0,
+            udata: token as u64,
+        }
+    }
+
     pub fn id(&self) -> Token {
         self.udata as usize
     }
+
+    /// A timeout inserted with `Poll::insert_timeout` expired.
+    pub fn is_timeout(&self) -> bool {
+        self.filter == ffi::EVFILT_USER
+    }
 }
 
 pub struct TcpStream {
@@ -170,12 +279,20 @@ impl AsRawFd for TcpStream {
     }
 }
 
+impl Source for TcpStream {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
 mod ffi {
     use super::*;
 
     pub const EVFILT_READ: i16 = -1;
     pub const EVFILT_TIMER: i16 = -7;
+    pub const EVFILT_USER: i16 = -10;
     pub const EV_ADD: u16 = 0x1;
+    pub const EV_DELETE: u16 = 0x2;
     pub const EV_ENABLE: u16 = 0x4;
     pub const EV_ONESHOT: u16 = 0x10;
     pub const EV_CLEAR: u16 = 0x20;
@@ -202,18 +319,29 @@ mod ffi {
 
     pub type Event = Kevent;
     impl Event {
-        pub fn new_read_event(fd: RawFd, id: u64) -> Self {
+        pub fn new_read_event(fd: RawFd, id: u64, trigger_flags: u16) -> Self {
             Event {
                 ident: fd as u64,
                 filter: EVFILT_READ,
-                flags: EV_ADD | EV_ENABLE | EV_ONESHOT,
+                flags: EV_ADD | EV_ENABLE | trigger_flags,
                 fflags: 0,
                 data: 0,
                 udata: id,
             }
         }
 
-        pub fn new_wakeup_event() -> Self {
+        pub fn new_delete_read_event(fd: RawFd) -> Self {
+            Event {
+                ident: fd as u64,
+                filter: EVFILT_READ,
+                flags: EV_DELETE,
+                fflags: 0,
+                data: 0,
+                udata: 0,
+            }
+        }
+
+        pub fn new_wakeup_event(id: u64) -> Self {
             Event {
                 ident: 0,
                 filter: EVFILT_TIMER,
@@ -221,7 +349,7 @@ mod ffi {
                 fflags: 0,
                 // data is where our timeout will be set but we want to timeout immideately
                 data: 0,
-                udata: 0, // TODO: see if windows needs u32...
+                udata: id,
             }
         }
 
@@ -288,7 +416,7 @@ pub fn kqueue() -> io::Result<i32> {
 pub fn kevent(
     kq: RawFd,
     cl: &[ffi::Kevent],
-    el: &mut [ffi::Kevent],
+    el: *mut ffi::Kevent,
     n_events: i32,
     timeout_ms: Option<i32>,
 ) -> io::Result<usize> {
@@ -303,7 +431,7 @@ pub fn kevent(
             None => ptr::null(),
         };
 
-        ffi::kevent(kq, cl.as_ptr(), cl_len, el.as_mut_ptr(), n_events, timeout)
+        ffi::kevent(kq, cl.as_ptr(), cl_len, el, n_events, timeout)
     };
     if res < 0 {
         return Err(io::Error::last_os_error());
@@ -324,16 +452,15 @@ pub fn close(fd: RawFd) -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Interests;
+    use crate::{Interests, Trigger};
     #[test]
     fn create_kevent_works() {
         let selector = Selector::new().unwrap();
         let mut sock = TcpStream::connect("www.google.com:80").unwrap();
-        let poll_is_dead = Arc::new(AtomicBool::new(false));
-        let registrator = selector.registrator(poll_is_dead.clone());
+        let registrator = selector.registrator(Arc::new(LoopState::new()));
 
         registrator
-            .register(&mut sock, 1, Interests::READABLE)
+            .register(&mut sock, 1, Interests::READABLE, Trigger::Oneshot)
             .unwrap();
     }
 
@@ -347,14 +474,13 @@ mod tests {
                        \r\n";
         sock.write_all(request.as_bytes())
             .expect("Error writing to stream");
-        let poll_is_dead = Arc::new(AtomicBool::new(false));
-        let registrator = selector.registrator(poll_is_dead.clone());
+        let registrator = selector.registrator(Arc::new(LoopState::new()));
 
         registrator
-            .register(&sock, 99, Interests::READABLE)
+            .register(&mut sock, 99, Interests::READABLE, Trigger::Oneshot)
             .unwrap();
 
-        let mut events = vec![Event::zero()];
+        let mut events = Events::with_capacity(1);
 
         selector
             .select(&mut events, None)
@@ -374,14 +500,13 @@ mod tests {
         sock.write_all(request.as_bytes())
             .expect("Error writing to stream");
 
-        let poll_is_dead = Arc::new(AtomicBool::new(false));
-        let registrator = selector.registrator(poll_is_dead.clone());
+        let registrator = selector.registrator(Arc::new(LoopState::new()));
 
         registrator
-            .register(&sock, 100, Interests::READABLE)
+            .register(&mut sock, 100, Interests::READABLE, Trigger::Oneshot)
             .unwrap();
 
-        let mut events = vec![Event::zero()];
+        let mut events = Events::with_capacity(1);
 
         selector
             .select(&mut events, None)
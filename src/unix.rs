@@ -0,0 +1,38 @@
+#[cfg(target_os = "linux")]
+use crate::Registration;
+use std::os::unix::io::RawFd;
+
+/// Anything backed by a file descriptor that can be registered with a `Poll`. It's
+/// implemented for the I/O types in this crate. File descriptors from somewhere else (the
+/// standard library, other crates or C libraries) can be registered by wrapping them in a
+/// `SourceFd`.
+pub trait Source {
+    fn raw_fd(&self) -> RawFd;
+
+    /// Called every time the source has been registered or reregistered. An I/O type can
+    /// keep the `Registration` around to re-arm itself, like `TcpStream` does when a read
+    /// returns `WouldBlock`.
+    #[cfg(target_os = "linux")]
+    fn registered(&mut self, _registration: Registration) {}
+
+    /// Called when the source has been deregistered.
+    #[cfg(target_os = "linux")]
+    fn deregistered(&mut self) {}
+}
+
+/// Adapter that lets any raw file descriptor be registered:
+///
+/// ```ignore
+/// let fd = pipe_reader.as_raw_fd();
+/// registry.register(&mut SourceFd(&fd), token, Interests::READABLE, Trigger::Oneshot)?;
+/// ```
+///
+/// The caller is responsible for keeping the file descriptor open while it's registered.
+#[derive(Debug)]
+pub struct SourceFd<'a>(pub &'a RawFd);
+
+impl Source for SourceFd<'_> {
+    fn raw_fd(&self) -> RawFd {
+        *self.0
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{Event, Events, Token};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Every level of the wheel has 64 slots so a level's occupied slots fit in a `u64`.
+const SLOTS: usize = 64;
+const LEVELS: usize = 6;
+/// Timeouts further away than this (about 2.2 years at one tick per millisecond) are clamped.
+const MAX_TICKS: u64 = (1 << (6 * LEVELS)) - 1;
+
+/// Identifies a timeout inserted with `Poll::insert_timeout` so it can be cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timeout(u64);
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    token: Token,
+    when: u64,
+}
+
+#[derive(Debug)]
+struct Level {
+    /// Bit `n` is set when slot `n` has entries in it.
+    occupied: u64,
+    slots: Vec<Vec<Entry>>,
+}
+
+/// A hierarchical timer wheel counting time in ticks of one millisecond since it was created.
+///
+/// Level 0 has one slot per tick for the next 64 ticks, level 1 one slot per 64 ticks for the
+/// next 64 * 64 ticks and so on. A timeout is put on the lowest level that covers its deadline
+/// and moved down a level each time the wheel reaches its slot, until it expires from level 0.
+/// Inserting and cancelling is cheap and finding the next deadline only looks at one `u64` per
+/// level, no matter how many timeouts there are.
+#[derive(Debug)]
+pub(crate) struct TimerWheel {
+    start: Instant,
+    /// The tick the wheel has processed timeouts up to.
+    elapsed: u64,
+    levels: Vec<Level>,
+    /// The level and slot of every timeout that hasn't expired so it can be cancelled.
+    pending: HashMap<u64, (usize, usize)>,
+    /// Timeouts that expired but haven't been handed out as events yet.
+    expired: VecDeque<Entry>,
+    next_id: u64,
+}
+
+impl TimerWheel {
+    pub(crate) fn new() -> Self {
+        let levels = (0..LEVELS)
+            .map(|_| Level {
+                occupied: 0,
+                slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+            })
+            .collect();
+
+        TimerWheel {
+            start: Instant::now(),
+            elapsed: 0,
+            levels,
+            pending: HashMap::new(),
+            expired: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, token: Token, after: Duration) -> Timeout {
+        // Rounding up means a timeout never expires early. A deadline too far away to even
+        // represent is clamped like any other far away timeout in `insert_at`.
+        let when = Instant::now()
+            .checked_add(after)
+            .map_or(u64::MAX, |deadline| millis_ceil(deadline - self.start));
+        self.insert_at(token, when)
+    }
+
+    fn insert_at(&mut self, token: Token, when: u64) -> Timeout {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let when = when.min(self.elapsed + MAX_TICKS);
+        self.place(Entry { id, token, when });
+        Timeout(id)
+    }
+
+    /// Returns `false` if the timeout already expired and was delivered, or was cancelled.
+    pub(crate) fn cancel(&mut self, timeout: Timeout) -> bool {
+        if let Some((level, slot)) = self.pending.remove(&timeout.0) {
+            let level = &mut self.levels[level];
+            level.slots[slot].retain(|entry| entry.id != timeout.0);
+            if level.slots[slot].is_empty() {
+                level.occupied &= !(1 << slot);
+            }
+            return true;
+        }
+
+        match self.expired.iter().position(|entry| entry.id == timeout.0) {
+            Some(i) => {
+                self.expired.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// How long until the next timeout expires, `Duration::ZERO` if there are expired
+    /// timeouts waiting to be delivered and `None` if there are no timeouts at all.
+    pub(crate) fn next_timeout(&self) -> Option<Duration> {
+        if !self.expired.is_empty() {
+            return Some(Duration::ZERO);
+        }
+
+        self.next_expiration().map(|(_, _, deadline)| {
+            (self.start + Duration::from_millis(deadline)).saturating_duration_since(Instant::now())
+        })
+    }
+
+    /// Moves the wheel forward to the current time and adds an event for every expired timeout
+    /// to `events` as long as there is room. The rest are delivered by the next call.
+    pub(crate) fn expire(&mut self, events: &mut Events) {
+        let now = (Instant::now() - self.start).as_millis() as u64;
+        self.advance(now);
+
+        while events.len() < events.capacity() {
+            match self.expired.pop_front() {
+                Some(entry) => events.push(Event::timeout(entry.token)),
+                None => break,
+            }
+        }
+    }
+
+    fn advance(&mut self, now: u64) {
+        while let Some((level, slot, deadline)) = self.next_expiration() {
+            if deadline > now {
+                break;
+            }
+
+            self.elapsed = self.elapsed.max(deadline);
+            self.levels[level].occupied &= !(1 << slot);
+            let entries = std::mem::take(&mut self.levels[level].slots[slot]);
+            for entry in entries {
+                self.pending.remove(&entry.id);
+                // Entries that haven't expired yet move down to a lower level
+                self.place(entry);
+            }
+        }
+
+        self.elapsed = self.elapsed.max(now);
+    }
+
+    fn place(&mut self, entry: Entry) {
+        if entry.when <= self.elapsed {
+            self.expired.push_back(entry);
+            return;
+        }
+
+        let level = level_for(self.elapsed, entry.when);
+        let slot = ((entry.when >> (6 * level)) % SLOTS as u64) as usize;
+        self.pending.insert(entry.id, (level, slot));
+        self.levels[level].occupied |= 1 << slot;
+        self.levels[level].slots[slot].push(entry);
+    }
+
+    /// Finds the first occupied slot and the tick the wheel reaches it. Any timeout on a lower
+    /// level expires before every timeout on a higher level, so the lowest level with an
+    /// occupied slot has the next one.
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        self.levels
+            .iter()
+            .enumerate()
+            .find(|(_, level)| level.occupied != 0)
+            .map(|(i, level)| {
+                let slot_range = 1 << (6 * i);
+                let level_range = slot_range * SLOTS as u64;
+                let now_slot = ((self.elapsed / slot_range) % SLOTS as u64) as u32;
+                let slot = (level.occupied.rotate_right(now_slot).trailing_zeros() + now_slot)
+                    as usize
+                    % SLOTS;
+
+                let level_start = self.elapsed & !(level_range - 1);
+                let mut deadline = level_start + slot as u64 * slot_range;
+                if deadline <= self.elapsed {
+                    // Only happens on the top level, a timeout clamped to `MAX_TICKS` can end
+                    // up in a slot "before" the current one, which means the next rotation.
+                    deadline += level_range;
+                }
+                (i, slot, deadline)
+            })
+    }
+}
+
+/// Converts to whole milliseconds, rounding up and saturating at `u64::MAX`.
+pub(crate) fn millis_ceil(duration: Duration) -> u64 {
+    duration
+        .as_nanos()
+        .div_ceil(1_000_000)
+        .min(u64::MAX as u128) as u64
+}
+
+/// The level is decided by the most significant bit that differs between now and the deadline.
+fn level_for(elapsed: u64, when: u64) -> usize {
+    let masked = ((elapsed ^ when) | (SLOTS as u64 - 1)).min(MAX_TICKS - 1);
+    let significant = 63 - masked.leading_zeros() as usize;
+    significant / 6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expire_at(wheel: &mut TimerWheel, now: u64) -> Vec<Token> {
+        wheel.advance(now);
+        wheel.expired.drain(..).map(|entry| entry.token).collect()
+    }
+
+    #[test]
+    fn expires_in_order_across_levels() {
+        let mut wheel = TimerWheel::new();
+        wheel.insert_at(3, 300_000);
+        wheel.insert_at(1, 10);
+        wheel.insert_at(2, 5_000);
+
+        assert!(expire_at(&mut wheel, 9).is_empty());
+        assert_eq!(expire_at(&mut wheel, 10), vec![1]);
+        assert!(expire_at(&mut wheel, 4_999).is_empty());
+        assert_eq!(expire_at(&mut wheel, 5_000), vec![2]);
+        assert!(expire_at(&mut wheel, 299_999).is_empty());
+        assert_eq!(expire_at(&mut wheel, 300_000), vec![3]);
+        assert!(wheel.next_expiration().is_none());
+    }
+
+    #[test]
+    fn large_jump_expires_everything_due() {
+        let mut wheel = TimerWheel::new();
+        for (token, when) in [(1, 70), (2, 64 * 64 + 1), (3, 64 * 64 * 64 * 3)].iter() {
+            wheel.insert_at(*token, *when);
+        }
+
+        assert_eq!(expire_at(&mut wheel, 64 * 64 * 64 * 4), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn cancelled_timeouts_dont_expire() {
+        let mut wheel = TimerWheel::new();
+        let first = wheel.insert_at(1, 100);
+        wheel.insert_at(2, 100);
+
+        assert!(wheel.cancel(first));
+        assert!(!wheel.cancel(first));
+        assert_eq!(expire_at(&mut wheel, 100), vec![2]);
+    }
+
+    #[test]
+    fn far_timeouts_are_clamped() {
+        let mut wheel = TimerWheel::new();
+        wheel.insert_at(1, u64::MAX);
+
+        assert!(expire_at(&mut wheel, MAX_TICKS - 1).is_empty());
+        assert_eq!(expire_at(&mut wheel, MAX_TICKS), vec![1]);
+    }
+}
@@ -1,12 +1,11 @@
 #![allow(non_camel_case_types)]
 #![allow(dead_code)]
 
-use crate::{Interests, Token};
+use crate::{Error, Events, Interests, LoopState, Token, Trigger, CLOSE_TOKEN};
 use std::collections::LinkedList;
 use std::io::{self, Read, Write};
 use std::net;
 use std::os::windows::io::{AsRawSocket, RawSocket};
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 pub type Event = ffi::OVERLAPPED_ENTRY;
@@ -89,66 +88,131 @@ impl AsRawSocket for TcpStream {
     }
 }
 
+/// Something that can be registered with a `Poll`. IOCP needs access to the buffers owned by
+/// the I/O object when we register interest, so on Windows only the crate's own `TcpStream`
+/// can be registered.
+pub trait Source {
+    #[doc(hidden)]
+    fn as_tcp_stream(&mut self) -> &mut TcpStream;
+}
+
+impl Source for TcpStream {
+    fn as_tcp_stream(&mut self) -> &mut TcpStream {
+        self
+    }
+}
+
+/// Registers sources with a `Poll` from any thread. It's cheap to clone, so every thread that
+/// needs one can have its own, and they can all be used at the same time. It keeps the
+/// completion port open, but once the `Poll` is dropped every registration fails with
+/// `Error::Closed`.
+#[derive(Debug, Clone)]
 pub struct Registrator {
-    completion_port: isize,
-    is_poll_dead: Arc<AtomicBool>,
+    completion_port: Arc<CompletionPort>,
+    state: Arc<LoopState>,
 }
 
 impl Registrator {
-    pub fn register(
+    pub fn register<S: Source + ?Sized>(
         &self,
-        soc: &mut TcpStream,
+        source: &mut S,
         token: usize,
         interests: Interests,
-    ) -> io::Result<()> {
-        if self.is_poll_dead.load(Ordering::SeqCst) {
-            return Err(io::Error::new(
-                io::ErrorKind::Interrupted,
-                "Poll instance is dead.",
-            ));
-        }
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        iocp_register(
+            self.completion_port.0,
+            source.as_tcp_stream(),
+            token,
+            interests,
+            trigger,
+        )
+        .map_err(Error::Io)
+    }
 
-        ffi::create_io_completion_port(soc.as_raw_socket(), self.completion_port, 0)?;
+    /// IOCP is completion based, so "re-arming" a socket means queuing a new read on it.
+    pub fn reregister<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: usize,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        iocp_queue_operation(source.as_tcp_stream(), token, interests, trigger).map_err(Error::Io)
+    }
 
-        let op = ffi::Operation::new(token);
-        soc.operations.push_back(op);
+    pub fn deregister<S: Source + ?Sized>(&self, _source: &mut S) -> Result<(), Error> {
+        self.check_poll_alive()?;
+        iocp_deregister().map_err(Error::Io)
+    }
 
-        if interests.is_readable() {
-            ffi::wsa_recv(
-                soc.as_raw_socket(),
-                &mut soc.wsabuf,
-                soc.operations.back_mut().unwrap(),
-            )?;
-        } else {
-            unimplemented!();
+    fn check_poll_alive(&self) -> Result<(), Error> {
+        if !self.state.is_open() {
+            return Err(Error::Closed);
         }
 
         Ok(())
     }
 
-    /// NOTE: An alternative solution is to use the `CompletionKey` to signal that
-    /// this is a close event. We don't use it for anything else so it is a
-    /// good candidate to use for timers and special events like this
-    pub fn close_loop(&self) -> io::Result<()> {
-        if self
-            .is_poll_dead
-            .compare_and_swap(false, true, Ordering::SeqCst)
-        {
-            return Err(io::Error::new(
-                io::ErrorKind::Interrupted,
-                "Poll instance is dead.",
-            ));
+    /// Shuts down the event loop gracefully. The `Poll` wakes up and still returns the events
+    /// that were ready, then `Error::Closed` from every call after that.
+    ///
+    /// Returns `Error::Closed` if the loop is already shut down.
+    pub fn close_loop(&self) -> Result<(), Error> {
+        if !self.state.close() {
+            return Err(Error::Closed);
         }
-        let mut overlapped = ffi::WSAOVERLAPPED::zeroed();
-        ffi::post_queued_completion_status(self.completion_port, 0, 0, &mut overlapped)?;
+
+        self.wake_poll()
+    }
+
+    /// Shuts down the event loop right away. The `Poll` wakes up and returns `Error::Closed`,
+    /// events that were ready are dropped.
+    ///
+    /// Returns `Error::Closed` if the loop is already shut down.
+    pub fn abort_loop(&self) -> Result<(), Error> {
+        if !self.state.abort() {
+            return Err(Error::Closed);
+        }
+
+        self.wake_poll()
+    }
+
+    /// Like a timeout the wakeup has no `OVERLAPPED` and its token in the `CompletionKey`.
+    fn wake_poll(&self) -> Result<(), Error> {
+        ffi::post_queued_completion_status(
+            self.completion_port.0,
+            0,
+            CLOSE_TOKEN,
+            std::ptr::null_mut(),
+        )?;
         Ok(())
     }
 }
 
-// possible Arc<InnerSelector> needed
+/// The completion port handle, shared by the `Selector` and its `Registrator`s and closed when
+/// the last of them is dropped.
 #[derive(Debug)]
+struct CompletionPort(isize);
+
+impl Drop for CompletionPort {
+    fn drop(&mut self) {
+        match ffi::close_handle(self.0) {
+            Ok(_) => (),
+            Err(e) => {
+                if !std::thread::panicking() {
+                    panic!("{}", e);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Selector {
-    completion_port: isize,
+    completion_port: Arc<CompletionPort>,
 }
 
 impl Selector {
@@ -156,23 +220,52 @@ impl Selector {
         // set up the queue
         let completion_port = ffi::create_completion_port()?;
 
-        Ok(Selector { completion_port })
+        Ok(Selector {
+            completion_port: Arc::new(CompletionPort(completion_port)),
+        })
     }
 
-    pub fn registrator(&self, is_poll_dead: Arc<AtomicBool>) -> Registrator {
+    pub(crate) fn registrator(&self, state: Arc<LoopState>) -> Registrator {
         Registrator {
-            completion_port: self.completion_port,
-            is_poll_dead,
+            completion_port: self.completion_port.clone(),
+            state,
         }
     }
 
+    pub fn register<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: usize,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        iocp_register(
+            self.completion_port.0,
+            source.as_tcp_stream(),
+            token,
+            interests,
+            trigger,
+        )
+        .map_err(Error::Io)
+    }
+
+    pub fn reregister<S: Source + ?Sized>(
+        &self,
+        source: &mut S,
+        token: usize,
+        interests: Interests,
+        trigger: Trigger,
+    ) -> Result<(), Error> {
+        iocp_queue_operation(source.as_tcp_stream(), token, interests, trigger).map_err(Error::Io)
+    }
+
+    pub fn deregister<S: Source + ?Sized>(&self, _source: &mut S) -> Result<(), Error> {
+        iocp_deregister().map_err(Error::Io)
+    }
+
     /// Blocks until an Event has occured. Never times out. We could take a parameter
     /// for a timeout and pass it on but we'll not do that in our example.
-    pub fn select(
-        &mut self,
-        events: &mut Vec<ffi::OVERLAPPED_ENTRY>,
-        timeout: Option<i32>,
-    ) -> io::Result<()> {
+    pub fn select(&mut self, events: &mut Events, timeout: Option<i32>) -> io::Result<()> {
         // calling GetQueueCompletionStatus will either return a handle to a "port" ready to read or
         // block if the queue is empty.
 
@@ -184,8 +277,8 @@ impl Selector {
         let ul_count = events.capacity() as u32;
 
         let removed_res = ffi::get_queued_completion_status_ex(
-            self.completion_port as isize,
-            events,
+            self.completion_port.0,
+            events.as_mut_ptr(),
             ul_count,
             timeout,
             false,
@@ -209,17 +302,48 @@ impl Selector {
     }
 }
 
-impl Drop for Selector {
-    fn drop(&mut self) {
-        match ffi::close_handle(self.completion_port) {
-            Ok(_) => (),
-            Err(e) => {
-                if !std::thread::panicking() {
-                    panic!(e);
-                }
-            }
-        }
+fn iocp_register(
+    completion_port: isize,
+    soc: &mut TcpStream,
+    token: usize,
+    interests: Interests,
+    trigger: Trigger,
+) -> io::Result<()> {
+    ffi::create_io_completion_port(soc.as_raw_socket(), completion_port, 0)?;
+    iocp_queue_operation(soc, token, interests, trigger)
+}
+
+/// Every queued operation completes exactly once, so IOCP always behaves like
+/// `Trigger::Oneshot` and the trigger is ignored.
+fn iocp_queue_operation(
+    soc: &mut TcpStream,
+    token: usize,
+    interests: Interests,
+    _trigger: Trigger,
+) -> io::Result<()> {
+    let op = ffi::Operation::new(token);
+    soc.operations.push_back(op);
+
+    if interests.is_readable() {
+        ffi::wsa_recv(
+            soc.as_raw_socket(),
+            &mut soc.wsabuf,
+            soc.operations.back_mut().unwrap(),
+        )?;
+    } else {
+        unimplemented!();
     }
+
+    Ok(())
+}
+
+/// A socket can't be disassociated from a completion port, it's removed when the socket
+/// is closed.
+fn iocp_deregister() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Deregistering is not supported with IOCP, close the socket instead.",
+    ))
 }
 
 mod ffi {
@@ -251,12 +375,31 @@ mod ffi {
     }
 
     impl OVERLAPPED_ENTRY {
+        /// Expired timeouts don't have an operation, we keep the token in the completion key.
+        pub(crate) fn timeout(token: Token) -> Self {
+            OVERLAPPED_ENTRY {
+                lp_completion_key: token as *mut usize,
+                lp_overlapped: ptr::null_mut(),
+                internal: 0,
+                bytes_transferred: 0,
+            }
+        }
+
         pub fn id(&self) -> Token {
+            if self.is_timeout() {
+                return self.lp_completion_key as Token;
+            }
+
             // TODO: this might be solvable wihtout sacrifising so much of Rust safety guarantees
             let operation: &Operation = unsafe { &*(self.lp_overlapped as *const Operation) };
             operation.token
         }
 
+        /// A timeout inserted with `Poll::insert_timeout` expired.
+        pub fn is_timeout(&self) -> bool {
+            self.lp_overlapped.is_null()
+        }
+
         pub(crate) fn zeroed() -> Self {
             OVERLAPPED_ENTRY {
                 lp_completion_key: ptr::null_mut(),
@@ -484,7 +627,7 @@ mod ffi {
         completion_port: isize,
         bytes_to_transfer: u32,
         completion_key: usize,
-        overlapped_ptr: &mut WSAOVERLAPPED,
+        overlapped_ptr: *mut WSAOVERLAPPED,
     ) -> io::Result<()> {
         let res = unsafe {
             PostQueuedCompletionStatus(
@@ -515,7 +658,7 @@ mod ffi {
     /// The number of items actually removed from the queue
     pub fn get_queued_completion_status_ex(
         completion_port: isize,
-        completion_port_entries: &mut [OVERLAPPED_ENTRY],
+        completion_port_entries: *mut OVERLAPPED_ENTRY,
         ul_count: u32,
         timeout: Option<u32>,
         alertable: bool,
@@ -529,7 +672,7 @@ mod ffi {
         let res = unsafe {
             GetQueuedCompletionStatusEx(
                 completion_port,
-                completion_port_entries.as_mut_ptr(),
+                completion_port_entries,
                 ul_count,
                 &mut ul_num_entries_removed,
                 timeout,
@@ -552,14 +695,13 @@ mod tests {
     #[test]
     fn selector_new_creates_valid_port() {
         let selector = Selector::new().expect("create completion port failed");
-        assert!(selector.completion_port > 0);
+        assert!(selector.completion_port.0 > 0);
     }
 
     #[test]
     fn selector_register() {
         let selector = Selector::new().expect("create completion port failed");
-        let poll_is_alive = Arc::new(AtomicBool::new(false));
-        let registrator = selector.registrator(poll_is_alive.clone());
+        let registrator = selector.registrator(Arc::new(LoopState::new()));
         let mut sock: TcpStream = TcpStream::connect("slowwly.robertomurray.co.uk:80").unwrap();
         let request = "GET /delay/1000/url/http://www.google.com HTTP/1.1\r\n\
                        Host: slowwly.robertomurray.co.uk\r\n\
@@ -569,15 +711,14 @@ mod tests {
             .expect("Error writing to stream");
 
         registrator
-            .register(&mut sock, 1, Interests::READABLE)
+            .register(&mut sock, 1, Interests::READABLE, Trigger::Oneshot)
             .expect("Error registering sock read event");
     }
 
     #[test]
     fn selector_select() {
         let mut selector = Selector::new().expect("create completion port failed");
-        let poll_is_alive = Arc::new(AtomicBool::new(false));
-        let registrator = selector.registrator(poll_is_alive.clone());
+        let registrator = selector.registrator(Arc::new(LoopState::new()));
         let mut sock: TcpStream = TcpStream::connect("slowwly.robertomurray.co.uk:80").unwrap();
         let request = "GET /delay/1000/url/http://www.google.com HTTP/1.1\r\n\
                        Host: slowwly.robertomurray.co.uk\r\n\
@@ -587,13 +728,12 @@ mod tests {
             .expect("Error writing to stream");
 
         registrator
-            .register(&mut sock, 2, Interests::READABLE)
+            .register(&mut sock, 2, Interests::READABLE, Trigger::Oneshot)
             .expect("Error registering sock read event");
-        let entry = ffi::OVERLAPPED_ENTRY::zeroed();
-        let mut events: Vec<ffi::OVERLAPPED_ENTRY> = vec![entry; 255];
+        let mut events = Events::with_capacity(255);
         selector.select(&mut events, None).expect("Select failed");
 
-        for event in events {
+        for event in &events {
             println!("COMPL_KEY: {:?}", event.id());
             assert_eq!(2, event.id());
         }
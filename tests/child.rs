@@ -0,0 +1,121 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Child, Events, Interests, Poll, StdioTokens, Trigger};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+#[test]
+fn readable_when_child_exits() {
+    let mut poll = Poll::new().unwrap();
+    let mut child = Child::spawn(Command::new("sh").args(["-c", "exit 3"])).unwrap();
+    poll.registry()
+        .register(&mut child, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(5000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_readable());
+
+    let status = child.try_wait().unwrap().expect("child has exited");
+    assert_eq!(status.code(), Some(3));
+}
+
+#[test]
+fn try_wait_doesnt_block() {
+    let mut poll = Poll::new().unwrap();
+    let mut child = Child::spawn(Command::new("sleep").arg("10")).unwrap();
+    poll.registry()
+        .register(&mut child, 2, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    assert!(child.try_wait().unwrap().is_none());
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+
+    child.kill().unwrap();
+    poll.poll(&mut events, Some(5000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 2);
+    assert!(!child.try_wait().unwrap().unwrap().success());
+}
+
+#[test]
+fn piped_stdio_is_non_blocking() {
+    let mut poll = Poll::new().unwrap();
+    let mut child = Child::spawn(
+        Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped()),
+    )
+    .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    assert!(child.stderr.is_none());
+
+    poll.registry()
+        .register(&mut stdout, 3, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut buf = [0; 16];
+    assert_eq!(
+        stdout.read(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    stdin.write_all(b"hello").unwrap();
+    drop(stdin);
+
+    let mut events = Events::with_capacity(16);
+    let mut output = vec![];
+    loop {
+        poll.poll(&mut events, Some(5000)).unwrap();
+        assert_eq!(events.len(), 1);
+        match stdout.read_to_end(&mut output) {
+            Ok(_) => break,
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) => panic!("read err: {}", e),
+        }
+    }
+    assert_eq!(output, b"hello");
+}
+
+#[test]
+fn spawn_registered_registers_piped_stdio() {
+    let mut poll = Poll::new().unwrap();
+    let tokens = StdioTokens {
+        stdin: 4,
+        stdout: 5,
+        stderr: 6,
+    };
+    let mut child = Child::spawn_registered(
+        Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped()),
+        poll.registry(),
+        tokens,
+        Trigger::Oneshot,
+    )
+    .unwrap();
+
+    // Nothing has been written yet, so only stdin is ready
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 4);
+    assert!(events[0].is_writable());
+
+    child.stdin.as_mut().unwrap().write_all(b"hello").unwrap();
+    poll.poll(&mut events, Some(5000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 5);
+    assert!(events[0].is_readable());
+
+    let mut buf = [0; 16];
+    let n = child.stdout.as_mut().unwrap().read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"hello");
+    child.kill().unwrap();
+}
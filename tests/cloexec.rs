@@ -0,0 +1,62 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{pipe, Interests, Poll, TcpListener, TcpStream, Trigger, UnixStream, Waker};
+use std::collections::HashSet;
+use std::fs;
+use std::os::unix::io::AsRawFd;
+use std::process::Command;
+
+/// What `/proc/self/fd` says the fd points to, e.g. `socket:[1234]` or `anon_inode:[eventpoll]`.
+fn fd_target(fd: &impl AsRawFd) -> String {
+    let path = fs::read_link(format!("/proc/self/fd/{}", fd.as_raw_fd())).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn child_processes_inherit_no_fds() {
+    // Also creates the eventfd `close_loop` uses to wake up the poll thread
+    let poll = Poll::new().unwrap();
+    let _waker = Waker::new(poll.registry(), 1).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    poll.registry()
+        .register(&mut stream, 2, Interests::WRITABLE, Trigger::Oneshot)
+        .unwrap();
+    let accepted = loop {
+        match listener.accept() {
+            Ok((accepted, _)) => break accepted,
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::yield_now(),
+            Err(e) => panic!("accept err: {}", e),
+        }
+    };
+    let (unix_a, unix_b) = UnixStream::pair().unwrap();
+    let (sender, receiver) = pipe().unwrap();
+
+    let ours: HashSet<String> = vec![
+        fd_target(&listener),
+        fd_target(&stream),
+        fd_target(&accepted),
+        fd_target(&unix_a),
+        fd_target(&unix_b),
+        fd_target(&sender),
+        fd_target(&receiver),
+    ]
+    .into_iter()
+    .collect();
+
+    // `$$` is the shell itself, so this lists the fds the child process was started with. The
+    // glob can list the fd the shell reads the directory with, which is closed by the time we
+    // get to it, so we don't care if `readlink` fails.
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("for fd in /proc/$$/fd/*; do readlink $fd; done; true")
+        .output()
+        .unwrap();
+    let inherited = String::from_utf8(output.stdout).unwrap();
+
+    for target in inherited.lines() {
+        assert!(!ours.contains(target), "inherited {}", target);
+        assert!(!target.starts_with("anon_inode:"), "inherited {}", target);
+    }
+}
@@ -0,0 +1,120 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::connected_pair;
+use minimio::{Error, Events, Interests, Poll, Trigger, UnixStream};
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn poll_returns_closed_after_close_loop() {
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+    let mut events = Events::with_capacity(16);
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        registrator.close_loop().unwrap();
+        registrator
+    });
+
+    let err = poll.poll(&mut events, None).unwrap_err();
+    assert!(matches!(err, Error::Closed));
+
+    let registrator = handle.join().unwrap();
+    assert!(matches!(registrator.close_loop(), Err(Error::Closed)));
+}
+
+#[test]
+fn close_loop_delivers_ready_events() {
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+    let mut events = Events::with_capacity(16);
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    poll.registry()
+        .register(&mut a, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    b.write_all(b"ping").unwrap();
+    registrator.close_loop().unwrap();
+
+    assert_eq!(poll.poll(&mut events, None).unwrap(), 1);
+    assert_eq!(events[0].id(), 1);
+    let err = poll.poll(&mut events, None).unwrap_err();
+    assert!(matches!(err, Error::Closed));
+    assert!(events.is_empty());
+}
+
+#[test]
+fn abort_loop_drops_ready_events() {
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+    let mut events = Events::with_capacity(16);
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    poll.registry()
+        .register(&mut a, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    b.write_all(b"ping").unwrap();
+    registrator.abort_loop().unwrap();
+
+    let err = poll.poll(&mut events, None).unwrap_err();
+    assert!(matches!(err, Error::Closed));
+    assert!(matches!(registrator.close_loop(), Err(Error::Closed)));
+}
+
+#[test]
+fn registering_with_closed_loop_fails() {
+    let poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+    let (mut stream, _peer) = connected_pair();
+
+    registrator.close_loop().unwrap();
+    let err = registrator
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap_err();
+    assert!(matches!(err, Error::Closed));
+}
+
+#[test]
+fn converts_to_io_error() {
+    let err: io::Error = Error::NotRegistered.into();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let err: io::Error = Error::AlreadyRegistered.into();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    let err: io::Error = Error::Io(io::Error::from(io::ErrorKind::Interrupted)).into();
+    assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+}
+
+#[test]
+fn registering_after_poll_is_dropped_fails() {
+    let poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+    let (mut stream, _peer) = connected_pair();
+
+    drop(poll);
+    let err = registrator
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap_err();
+    assert!(matches!(err, Error::Closed));
+}
+
+#[test]
+fn registered_source_outlives_poll() {
+    let poll = Poll::new().unwrap();
+    let (mut stream, _peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    // The registration keeps the epoll instance open so re-arming on `WouldBlock` can't hit
+    // an fd the OS handed out after the `Poll` was dropped
+    drop(poll);
+    let _other = Poll::new().unwrap();
+    let err = stream.read(&mut [0; 16]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+}
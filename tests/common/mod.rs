@@ -0,0 +1,76 @@
+//! Helpers shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use minimio::TcpStream;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+use std::time::Duration;
+
+/// A `TcpStream` connected to a blocking standard library stream we can write to.
+pub fn connected_pair() -> (TcpStream, std::net::TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (peer, _) = listener.accept().unwrap();
+    (stream, peer)
+}
+
+/// `TcpStream::connect` doesn't block on Linux, so we wait for the connection to be
+/// established before we send anything.
+#[cfg(target_os = "linux")]
+pub fn wait_until_connected(stream: &mut TcpStream) {
+    use minimio::{Events, Interests, Poll, Trigger};
+
+    let mut poll = Poll::new().unwrap();
+    poll.registry()
+        .register(stream, 0, Interests::WRITABLE, Trigger::Oneshot)
+        .expect("registration err.");
+    let mut events = Events::with_capacity(1);
+    poll.poll(&mut events, None).expect("poll err.");
+    poll.registry()
+        .deregister(stream)
+        .expect("deregistration err.");
+    assert!(stream.take_error().unwrap().is_none(), "connect err.");
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn wait_until_connected(_stream: &mut TcpStream) {}
+
+pub const HTTP_BODY: &[u8] = b"Hello from a slow server";
+
+/// Starts a local HTTP server that answers `connections` requests, each one after a delay so
+/// the response arrives after the client has started waiting for it. The connection is closed
+/// after the response, so the client can read to the end.
+pub fn slow_http_server(connections: usize) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let adr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().take(connections) {
+            let mut stream = stream.unwrap();
+            thread::spawn(move || {
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf).unwrap() {
+                        0 => return,
+                        n => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+
+                thread::sleep(Duration::from_millis(200));
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    HTTP_BODY.len()
+                );
+                stream.write_all(header.as_bytes()).unwrap();
+                stream.write_all(HTTP_BODY).unwrap();
+            });
+        }
+    });
+
+    adr
+}
+
+/// A request for `slow_http_server` that asks it to close the connection afterwards.
+pub const HTTP_REQUEST: &str = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
@@ -0,0 +1,48 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::connected_pair;
+use minimio::{Events, Interests, Poll, Trigger};
+use std::io::Write;
+use std::net::Shutdown;
+
+#[test]
+fn data_is_not_reported_as_closed() {
+    let mut poll = Poll::new().unwrap();
+
+    let (mut stream, mut peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    peer.write_all(b"data").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_readable());
+    assert!(!events[0].is_read_closed());
+    assert!(!events[0].is_write_closed());
+    assert!(!events[0].is_error());
+    assert!(!events[0].is_priority());
+}
+
+#[test]
+fn peer_hangup_is_reported_as_read_closed() {
+    let mut poll = Poll::new().unwrap();
+
+    let (mut stream, peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    peer.shutdown(Shutdown::Write).unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_read_closed());
+    assert!(!events[0].is_write_closed());
+    assert!(!events[0].is_error());
+}
@@ -0,0 +1,63 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Poll, Waker};
+
+fn ready_wakers(poll: &Poll, n: usize) -> Vec<Waker> {
+    (0..n)
+        .map(|token| {
+            let waker = Waker::new(poll.registry(), token).unwrap();
+            waker.wake().unwrap();
+            waker
+        })
+        .collect()
+}
+
+#[test]
+fn poll_respects_capacity() {
+    let mut poll = Poll::new().unwrap();
+    let _wakers = ready_wakers(&poll, 5);
+
+    let mut events = Events::with_capacity(2);
+    let n = poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(n, 2);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events.capacity(), 2);
+
+    // The rest is returned by the following polls
+    let mut tokens: Vec<usize> = events.iter().map(|e| e.id()).collect();
+    while tokens.len() < 5 {
+        poll.poll(&mut events, Some(1000)).unwrap();
+        assert!(events.len() <= 2);
+        tokens.extend(events.iter().map(|e| e.id()));
+    }
+
+    tokens.sort_unstable();
+    assert_eq!(tokens, vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn growable_events_grow_when_full() {
+    let mut poll = Poll::new().unwrap();
+    let _wakers = ready_wakers(&poll, 5);
+
+    let mut events = Events::growable(2);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events.capacity(), 4);
+
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events.capacity(), 4);
+}
+
+#[test]
+fn zero_capacity_is_usable() {
+    let mut poll = Poll::new().unwrap();
+    let _wakers = ready_wakers(&poll, 1);
+
+    let mut events = Events::with_capacity(0);
+    assert_eq!(events.capacity(), 1);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!((&events).into_iter().count(), 1);
+}
@@ -0,0 +1,37 @@
+use minimio::Interests;
+
+#[test]
+fn interests_combine_with_bitor() {
+    let mut interests = Interests::READABLE | Interests::WRITABLE;
+    assert!(interests.is_readable());
+    assert!(interests.is_writable());
+    assert!(!interests.is_priority());
+
+    interests |= Interests::PRIORITY;
+    assert!(interests.is_priority());
+    assert_eq!(
+        interests,
+        Interests::READABLE
+            .add(Interests::WRITABLE)
+            .add(Interests::PRIORITY)
+    );
+}
+
+#[test]
+fn interests_remove() {
+    let interests = Interests::READABLE | Interests::READ_CLOSED;
+    assert_eq!(
+        interests.remove(Interests::READABLE),
+        Some(Interests::READ_CLOSED)
+    );
+    assert_eq!(Interests::READABLE.remove(Interests::READABLE), None);
+    // A flag on its own isn't an interest
+    let exclusive = Interests::READABLE | Interests::EXCLUSIVE;
+    assert_eq!(exclusive.remove(Interests::READABLE), None);
+}
+
+#[test]
+fn interests_debug() {
+    let interests = Interests::READABLE | Interests::WRITABLE;
+    assert_eq!(format!("{:?}", interests), "READABLE | WRITABLE");
+}
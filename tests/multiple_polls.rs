@@ -0,0 +1,143 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Error, Events, Interests, Poll, SourceFd, TcpListener, Trigger, UnixStream};
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+const THREADS: usize = 4;
+
+#[test]
+fn handles_share_registrations() {
+    let poll = Poll::new().unwrap();
+    let mut handle = poll.clone_handle();
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    poll.registry()
+        .register(&mut a, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    // The event queue stays open while there are handles left
+    drop(poll);
+    b.write_all(b"ping").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    assert_eq!(handle.poll(&mut events, Some(1000)).unwrap(), 1);
+    assert_eq!(events[0].id(), 1);
+}
+
+#[test]
+fn oneshot_event_wakes_one_handle() {
+    let poll = Poll::new().unwrap();
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    poll.registry()
+        .register(&mut a, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let barrier = Arc::new(Barrier::new(THREADS + 1));
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let mut poll = poll.clone_handle();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let mut events = Events::with_capacity(16);
+                barrier.wait();
+                poll.poll(&mut events, Some(500)).unwrap()
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    thread::sleep(Duration::from_millis(100));
+    b.write_all(b"ping").unwrap();
+
+    let woken: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(woken, 1);
+}
+
+#[test]
+fn close_loop_closes_every_handle() {
+    let poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let mut poll = poll.clone_handle();
+            thread::spawn(move || {
+                let mut events = Events::with_capacity(16);
+                poll.poll(&mut events, None)
+            })
+        })
+        .collect();
+
+    thread::sleep(Duration::from_millis(50));
+    registrator.close_loop().unwrap();
+    for handle in handles {
+        assert!(matches!(handle.join().unwrap(), Err(Error::Closed)));
+    }
+}
+
+#[test]
+fn exclusive_registration_wakes_one_poll() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let fd = listener.as_raw_fd();
+
+    // Every thread has its own event queue with the same listener registered in it
+    let barrier = Arc::new(Barrier::new(THREADS + 1));
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let mut poll = Poll::new().unwrap();
+                poll.registry()
+                    .register(
+                        &mut SourceFd(&fd),
+                        1,
+                        Interests::READABLE | Interests::EXCLUSIVE,
+                        Trigger::Edge,
+                    )
+                    .unwrap();
+                let mut events = Events::with_capacity(16);
+                barrier.wait();
+                poll.poll(&mut events, Some(500)).unwrap()
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    thread::sleep(Duration::from_millis(100));
+    let _stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+    let woken: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(woken, 1);
+}
+
+#[test]
+fn invalid_exclusive_registrations_are_rejected() {
+    let poll = Poll::new().unwrap();
+    let (mut a, _b) = UnixStream::pair().unwrap();
+    let exclusive = Interests::READABLE | Interests::EXCLUSIVE;
+
+    let err = poll
+        .registry()
+        .register(&mut a, 1, exclusive, Trigger::Oneshot)
+        .unwrap_err();
+    assert!(matches!(err, Error::Io(ref e) if e.kind() == io::ErrorKind::InvalidInput));
+
+    let err = poll
+        .registry()
+        .register(&mut a, 1, exclusive | Interests::PRIORITY, Trigger::Edge)
+        .unwrap_err();
+    assert!(matches!(err, Error::Io(ref e) if e.kind() == io::ErrorKind::InvalidInput));
+
+    poll.registry()
+        .register(&mut a, 1, exclusive, Trigger::Edge)
+        .unwrap();
+    let err = poll
+        .registry()
+        .reregister(&mut a, 1, exclusive, Trigger::Edge)
+        .unwrap_err();
+    assert!(matches!(err, Error::Io(ref e) if e.kind() == io::ErrorKind::InvalidInput));
+}
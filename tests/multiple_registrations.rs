@@ -1,5 +1,10 @@
-use minimio::{Events, Interests, Poll, TcpStream};
+mod common;
+
+use common::{slow_http_server, wait_until_connected, HTTP_BODY, HTTP_REQUEST};
+use minimio::{Error, Events, Interests, Poll, TcpStream, Trigger};
+use std::cell::Cell;
 use std::io::{self, Read, Write};
+use std::rc::Rc;
 use std::sync::mpsc::channel;
 use std::thread;
 
@@ -24,13 +29,13 @@ fn multiple_registraions() {
             println!("POLLING");
             let will_close = false;
             println!("{:?}", poll);
-            match poll.poll(&mut events, Some(200)) {
+            match poll.poll(&mut events, None) {
                 Ok(..) => (),
-                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
-                    println!("INTERRUPTED: {}", e);
+                Err(Error::Closed) => {
+                    println!("CLOSED");
                     break;
                 }
-                Err(e) => panic!("Poll error: {:?}, {}", e.kind(), e),
+                Err(e) => panic!("Poll error: {}", e),
             };
             for event in &events {
                 let event_token = event.id();
@@ -46,23 +51,17 @@ fn multiple_registraions() {
     });
 
     // ===== THIS IS "APPLICATION" CODE USING OUR INFRASTRUCTURE =====
-    let mut stream = TcpStream::connect("slowwly.robertomurray.co.uk:80").unwrap();
-    let request = "GET /delay/1000/url/http://www.google.com HTTP/1.1\r\n\
-                   Host: slowwly.robertomurray.co.uk\r\n\
-                   Connection: close\r\n\
-                   \r\n";
+    let server = slow_http_server(2);
+    let mut stream = TcpStream::connect(server).unwrap();
+    wait_until_connected(&mut stream);
     stream
-        .write_all(request.as_bytes())
+        .write_all(HTTP_REQUEST.as_bytes())
         .expect("Error writing to stream");
 
-    let mut stream2 = TcpStream::connect("slowwly.robertomurray.co.uk:80").unwrap();
-
-    let request2 = "GET /delay/1000/url/http://www.google.com HTTP/1.1\r\n\
-                    Host: slowwly.robertomurray.co.uk\r\n\
-                    Connection: close\r\n\
-                    \r\n";
+    let mut stream2 = TcpStream::connect(server).unwrap();
+    wait_until_connected(&mut stream2);
     stream2
-        .write_all(request2.as_bytes())
+        .write_all(HTTP_REQUEST.as_bytes())
         .expect("Error writing to stream");
 
     // Mio does this
@@ -71,42 +70,67 @@ fn multiple_registraions() {
 
     // PROBLEM 2: We need to use registry here
     registrator
-        .register(&mut stream, provided_token, Interests::READABLE)
+        .register(
+            &mut stream,
+            provided_token,
+            Interests::READABLE,
+            Trigger::Oneshot,
+        )
         .expect("registration err.");
     registrator
-        .register(&mut stream2, provided_token2, Interests::READABLE)
+        .register(
+            &mut stream2,
+            provided_token2,
+            Interests::READABLE,
+            Trigger::Oneshot,
+        )
         .expect("registration err.");
     println!("HERE");
 
     // When we get notified that 10 is ready we can run this code
+    // The streams are non-blocking so a handler can run several times. It reads what's there
+    // and gets run again when more data arrives since `WouldBlock` re-arms the registration.
+    let finished = Rc::new(Cell::new(0));
+
+    let mut buffer = Vec::new();
+    let finished1 = finished.clone();
     rt.spawn(provided_token, move || {
-        let mut buffer = String::new();
-        stream.read_to_string(&mut buffer).unwrap();
-        assert!(!buffer.is_empty(), "Got an empty buffer");
-        println!("PROPOSED API:\n{}", buffer);
+        match stream.read_to_end(&mut buffer) {
+            Ok(_) => {
+                assert!(buffer.ends_with(HTTP_BODY), "Got an unexpected response");
+                println!("PROPOSED API:\n{}", String::from_utf8_lossy(&buffer));
+                finished1.set(finished1.get() + 1);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) => panic!("Stream read err: {}", e),
+        }
     });
 
-     rt.spawn(provided_token2, move || {
-        let mut buffer = String::new();
-        let mut buffer = [0u8; 2048* 2];
-        while stream2.read(&mut buffer).unwrap() > 0 {}
-        assert!(!buffer.is_empty(), "Got an empty buffer");
-        println!("PROPOSED API2:\n{}", String::from_utf8(buffer.to_vec()).unwrap());
+    let mut buffer2 = Vec::new();
+    let finished2 = finished.clone();
+    rt.spawn(provided_token2, move || {
+        match stream2.read_to_end(&mut buffer2) {
+            Ok(_) => {
+                assert!(buffer2.ends_with(HTTP_BODY), "Got an unexpected response");
+                println!("PROPOSED API2:\n{}", String::from_utf8_lossy(&buffer2));
+                finished2.set(finished2.get() + 1);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) => panic!("Stream read err: {}", e),
+        }
     });
 
     // ===== THIS WILL BE IN OUR MAIN EVENT LOOP ======
     // But we'll only check if we have gotten anything, not block
     println!("WAITING FOR EVENTS");
-    let mut counter = 0;
     while let Ok(recieved_token) = evt_reciever.recv() {
-        counter += 1;
         println!("RECIEVED EVENT: {:?}", recieved_token);
         // Running the code for event
         rt.run(recieved_token); // runs the code associated with event 10 in this case
                                 // let's close the event loop since we know we only have 1 event
 
-        // we close it after 2 events
-        if counter == 2 {
+        // we close it when both streams are read to the end
+        if finished.get() == 2 {
             registrator.close_loop().expect("close loop err.");
         }
     }
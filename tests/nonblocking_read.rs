@@ -0,0 +1,116 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::connected_pair;
+use minimio::{Events, Interests, Poll, Trigger};
+use std::io::{self, IoSliceMut, Read, Write};
+
+#[test]
+fn read_returns_would_block_instead_of_blocking() {
+    let (mut stream, _peer) = connected_pair();
+
+    let mut buf = [0; 16];
+    let err = stream.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+    let mut bufs = [IoSliceMut::new(&mut buf)];
+    let err = stream.read_vectored(&mut bufs).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn would_block_rearms_oneshot_registration() {
+    let mut poll = Poll::new().unwrap();
+    let (mut stream, mut peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    let mut buf = [0; 16];
+
+    for chunk in [&b"first"[..], &b"second"[..]].iter() {
+        peer.write_all(chunk).unwrap();
+
+        poll.poll(&mut events, Some(1000)).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id(), 1);
+
+        let n = stream.read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], *chunk);
+
+        // Drained, which re-arms the registration without calling `reregister`
+        let err = stream.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+}
+
+#[test]
+fn read_vectored_rearms_like_read() {
+    let mut poll = Poll::new().unwrap();
+    let (mut stream, mut peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    let (mut a, mut b) = ([0; 2], [0; 8]);
+
+    peer.write_all(b"hello").unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    assert_eq!(stream.read_vectored(&mut bufs).unwrap(), 5);
+    let err = stream.read_vectored(&mut bufs).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    assert_eq!(&a, b"he");
+    assert_eq!(&b[..3], b"llo");
+
+    peer.write_all(b"again").unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+}
+
+#[test]
+fn write_would_block_rearms_oneshot_registration() {
+    let mut poll = Poll::new().unwrap();
+    let (mut stream, mut peer) = connected_pair();
+    peer.set_nonblocking(true).unwrap();
+    poll.registry()
+        .register(&mut stream, 1, Interests::WRITABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_writable());
+
+    // Fill the send buffer, the `WouldBlock` re-arms the registration
+    let chunk = [0; 64 * 1024];
+    loop {
+        match stream.write(&chunk) {
+            Ok(_) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => panic!("write err: {}", e),
+        }
+    }
+
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match peer.read(&mut buf) {
+            Ok(n) => assert!(n > 0),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => panic!("read err: {}", e),
+        }
+    }
+
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_writable());
+}
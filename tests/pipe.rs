@@ -0,0 +1,94 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{pipe, Events, Interests, Poll, Trigger};
+use std::io::{self, Read, Write};
+
+#[test]
+fn receiver_is_readable_after_write() {
+    let mut poll = Poll::new().unwrap();
+    let (mut sender, mut receiver) = pipe().unwrap();
+    poll.registry()
+        .register(&mut receiver, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut buf = [0; 16];
+    assert_eq!(
+        receiver.read(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    sender.write_all(b"hello").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_readable());
+
+    let n = receiver.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"hello");
+}
+
+#[test]
+fn full_sender_becomes_writable_again() {
+    let mut poll = Poll::new().unwrap();
+    let (mut sender, mut receiver) = pipe().unwrap();
+    poll.registry()
+        .register(&mut sender, 2, Interests::WRITABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_writable());
+
+    // Fill the pipe, the `WouldBlock` re-arms the registration
+    let chunk = [0; 4096];
+    loop {
+        match sender.write(&chunk) {
+            Ok(_) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => panic!("write err: {}", e),
+        }
+    }
+
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+
+    let mut buf = vec![0; 64 * 1024];
+    let n = receiver.read(&mut buf).unwrap();
+    assert!(n > 0);
+
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 2);
+    assert!(events[0].is_writable());
+}
+
+#[test]
+fn dropping_sender_closes_receiver() {
+    let mut poll = Poll::new().unwrap();
+    let (sender, mut receiver) = pipe().unwrap();
+    poll.registry()
+        .register(&mut receiver, 3, Interests::READABLE, Trigger::Level)
+        .unwrap();
+
+    drop(sender);
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_write_closed());
+
+    let mut buf = [0; 16];
+    assert_eq!(receiver.read(&mut buf).unwrap(), 0);
+}
+
+#[test]
+fn dropping_receiver_breaks_pipe() {
+    let (mut sender, receiver) = pipe().unwrap();
+    drop(receiver);
+
+    let err = sender.write(b"hello").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+}
@@ -1,4 +1,7 @@
-use minimio::{Events, Interests, Poll, Registrator, TcpStream};
+mod common;
+
+use common::{slow_http_server, wait_until_connected, HTTP_REQUEST};
+use minimio::{Error, Events, Interests, Poll, Registrator, TcpStream, Trigger};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::{io, io::Read, io::Write, thread, thread::JoinHandle};
 
@@ -7,24 +10,38 @@ const TEST_TOKEN: usize = 10; // Hard coded for this test only
 #[test]
 fn proposed_api() {
     let (evt_sender, evt_reciever) = channel();
-    let mut reactor = Reactor::new(evt_sender);
+    let reactor = Reactor::new(evt_sender);
     let mut executor = Excutor::new(evt_reciever);
 
-    let mut stream = TcpStream::connect("slowwly.robertomurray.co.uk:80").unwrap();
-    let request = b"GET /delay/1000/url/http://www.google.com HTTP/1.1\r\nHost: slowwly.robertomurray.co.uk\r\nConnection: close\r\n\r\n";
+    let mut stream = TcpStream::connect(slow_http_server(1)).unwrap();
+    wait_until_connected(&mut stream);
 
-    stream.write_all(request).expect("Stream write err.");
+    stream
+        .write_all(HTTP_REQUEST.as_bytes())
+        .expect("Stream write err.");
 
     let registrator = reactor.registrator();
     registrator
-        .register(&mut stream, TEST_TOKEN, Interests::READABLE)
+        .register(
+            &mut stream,
+            TEST_TOKEN,
+            Interests::READABLE,
+            Trigger::Oneshot,
+        )
         .expect("registration err.");
 
+    let mut buffer = Vec::new();
     executor.suspend(TEST_TOKEN, move || {
-        let mut buffer = String::new();
-        stream.read_to_string(&mut buffer).unwrap();
-        registrator.close_loop().expect("close loop err.");
-        assert!(!buffer.is_empty(), "Got an empty buffer");
+        // The stream is non-blocking, so we read what's there and get resumed when more data
+        // arrives since `WouldBlock` re-arms the registration.
+        match stream.read_to_end(&mut buffer) {
+            Ok(_) => {
+                registrator.close_loop().expect("close loop err.");
+                assert!(!buffer.is_empty(), "Got an empty buffer");
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) => panic!("Stream read err: {}", e),
+        }
     });
 
     executor.block_on_all();
@@ -32,7 +49,7 @@ fn proposed_api() {
 
 struct Reactor {
     handle: Option<JoinHandle<()>>,
-    registrator: Option<Registrator>,
+    registrator: Registrator,
 }
 
 impl Reactor {
@@ -44,10 +61,10 @@ impl Reactor {
         let handle = thread::spawn(move || {
             let mut events = Events::with_capacity(1024);
             loop {
-                match poll.poll(&mut events, Some(200)) {
+                match poll.poll(&mut events, None) {
                     Ok(..) => (),
-                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => break,
-                    Err(e) => panic!("Poll error: {:?}, {}", e.kind(), e),
+                    Err(Error::Closed) => break,
+                    Err(e) => panic!("Poll error: {}", e),
                 };
                 for event in &events {
                     let event_token = event.id();
@@ -58,12 +75,12 @@ impl Reactor {
 
         Reactor {
             handle: Some(handle),
-            registrator: Some(registrator),
+            registrator,
         }
     }
 
-    fn registrator(&mut self) -> Registrator {
-        self.registrator.take().unwrap()
+    fn registrator(&self) -> Registrator {
+        self.registrator.clone()
     }
 }
 
@@ -0,0 +1,86 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, Registrator, Trigger, UnixStream};
+use std::collections::HashSet;
+use std::io::Write;
+use std::thread;
+
+const THREADS: usize = 8;
+const ITERATIONS: usize = 200;
+
+#[test]
+fn registrator_is_clone_send_and_sync() {
+    fn assert_clone_send_sync<T: Clone + Send + Sync>() {}
+    assert_clone_send_sync::<Registrator>();
+}
+
+#[test]
+fn registers_from_many_threads() {
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|token| {
+            let registrator = registrator.clone();
+            thread::spawn(move || {
+                let (mut a, mut b) = UnixStream::pair().unwrap();
+                registrator
+                    .register(&mut a, token, Interests::READABLE, Trigger::Oneshot)
+                    .unwrap();
+                b.write_all(b"ping").unwrap();
+                // The sockets have to stay open until the event has been polled
+                (a, b)
+            })
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut events = Events::with_capacity(THREADS);
+    while seen.len() < THREADS {
+        poll.poll(&mut events, Some(5000)).unwrap();
+        assert!(!events.is_empty(), "timed out waiting for events");
+        seen.extend(events.iter().map(|event| event.id()));
+    }
+
+    assert_eq!(seen, (0..THREADS).collect());
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn hammer_register_reregister_deregister() {
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|thread| {
+            let registrator = registrator.clone();
+            thread::spawn(move || {
+                for i in 0..ITERATIONS {
+                    let token = thread * ITERATIONS + i;
+                    let (mut a, _b) = UnixStream::pair().unwrap();
+                    registrator
+                        .register(&mut a, token, Interests::READABLE, Trigger::Oneshot)
+                        .unwrap();
+                    registrator
+                        .reregister(&mut a, token, Interests::WRITABLE, Trigger::Edge)
+                        .unwrap();
+                    registrator.deregister(&mut a).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    // Keep polling while the other threads register, only events from sources that were
+    // registered at the time can show up
+    let mut events = Events::with_capacity(64);
+    while !handles.iter().all(|handle| handle.is_finished()) {
+        poll.poll(&mut events, Some(10)).unwrap();
+        assert!(events.iter().all(|event| event.id() < THREADS * ITERATIONS));
+    }
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::connected_pair;
+use minimio::{Error, Events, Interests, Poll, Trigger};
+use std::io::Write;
+
+#[test]
+fn reregister_rearms_oneshot_registration() {
+    let mut poll = Poll::new().unwrap();
+
+    let (mut stream, mut peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    peer.write_all(b"first").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+
+    // The registration is disarmed after the first event even though there is still data
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+
+    poll.registry()
+        .reregister(&mut stream, 2, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 2);
+}
+
+#[test]
+fn deregister_stops_events() {
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let (mut stream, _peer) = connected_pair();
+    registrator
+        .register(&mut stream, 1, Interests::WRITABLE, Trigger::Oneshot)
+        .unwrap();
+    registrator.deregister(&mut stream).unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+}
+
+#[test]
+fn unknown_source_errors() {
+    let poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let (mut stream, _peer) = connected_pair();
+
+    let err = registrator
+        .reregister(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap_err();
+    assert!(matches!(err, Error::NotRegistered));
+
+    let err = poll.registry().deregister(&mut stream).unwrap_err();
+    assert!(matches!(err, Error::NotRegistered));
+
+    registrator
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+    let err = registrator
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap_err();
+    assert!(matches!(err, Error::AlreadyRegistered));
+}
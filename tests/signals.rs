@@ -0,0 +1,60 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, Signals, Trigger};
+use std::io;
+
+extern "C" {
+    // Sends the signal to the calling thread, which is the one blocking it
+    fn raise(sig: i32) -> i32;
+    fn getuid() -> u32;
+}
+
+#[test]
+fn receives_blocked_signals() {
+    let mut poll = Poll::new().unwrap();
+    let mut signals = Signals::new(&[Signals::SIGUSR1, Signals::SIGUSR2]).unwrap();
+    poll.registry()
+        .register(&mut signals, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    assert_eq!(
+        signals.receive().unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    assert_eq!(unsafe { raise(Signals::SIGUSR1) }, 0);
+    assert_eq!(unsafe { raise(Signals::SIGUSR2) }, 0);
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_readable());
+
+    let mut received = vec![];
+    loop {
+        match signals.receive() {
+            Ok(info) => {
+                assert_eq!(info.pid(), std::process::id());
+                assert_eq!(info.uid(), unsafe { getuid() });
+                received.push(info.signal());
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => panic!("receive err: {}", e),
+        }
+    }
+    received.sort();
+    assert_eq!(received, vec![Signals::SIGUSR1, Signals::SIGUSR2]);
+
+    // Re-armed by the `WouldBlock` above
+    assert_eq!(unsafe { raise(Signals::SIGUSR1) }, 0);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(signals.receive().unwrap().signal(), Signals::SIGUSR1);
+}
+
+#[test]
+fn invalid_signal_is_rejected() {
+    let err = Signals::new(&[0]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,92 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, Registration, Source, SourceFd, Trigger};
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+#[test]
+fn register_foreign_fd_with_source_fd() {
+    let mut poll = Poll::new().unwrap();
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    rx.set_nonblocking(true).unwrap();
+
+    let fd = rx.as_raw_fd();
+    poll.registry()
+        .register(&mut SourceFd(&fd), 7, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    tx.write_all(b"ping").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 7);
+    assert!(events[0].is_readable());
+
+    poll.registry().deregister(&mut SourceFd(&fd)).unwrap();
+}
+
+/// A source from outside the crate that re-arms itself like the crate's own I/O types.
+struct Rearming {
+    inner: UnixStream,
+    registration: Option<Registration>,
+}
+
+impl Read for Rearming {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if let Some(registration) = &self.registration {
+                    registration.rearm(self.inner.as_raw_fd())?;
+                }
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            res => res,
+        }
+    }
+}
+
+impl Source for Rearming {
+    fn raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+
+    fn registered(&mut self, registration: Registration) {
+        self.registration = Some(registration);
+    }
+
+    fn deregistered(&mut self) {
+        self.registration = None;
+    }
+}
+
+#[test]
+fn foreign_source_rearms_itself() {
+    let mut poll = Poll::new().unwrap();
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    rx.set_nonblocking(true).unwrap();
+    let mut source = Rearming {
+        inner: rx,
+        registration: None,
+    };
+    poll.registry()
+        .register(&mut source, 8, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    let mut buf = [0; 16];
+    for _ in 0..2 {
+        tx.write_all(b"ping").unwrap();
+        poll.poll(&mut events, Some(1000)).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id(), 8);
+
+        assert_eq!(source.read(&mut buf).unwrap(), 4);
+        let err = source.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+
+    poll.registry().deregister(&mut source).unwrap();
+    assert!(source.registration.is_none());
+}
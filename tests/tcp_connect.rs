@@ -0,0 +1,69 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, TcpStream, Trigger};
+use std::io;
+use std::net::TcpListener;
+
+fn wait_for_writable(poll: &mut Poll, stream: &mut TcpStream) {
+    poll.registry()
+        .register(stream, 1, Interests::WRITABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+}
+
+#[test]
+fn connect_completes_with_writable_event() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let adr = listener.local_addr().unwrap();
+    let mut poll = Poll::new().unwrap();
+
+    let mut stream = TcpStream::connect(adr).unwrap();
+    wait_for_writable(&mut poll, &mut stream);
+
+    assert!(stream.take_error().unwrap().is_none());
+    assert_eq!(stream.peer_addr().unwrap(), adr);
+}
+
+#[test]
+fn connect_ipv6() {
+    let listener = match TcpListener::bind("[::1]:0") {
+        Ok(listener) => listener,
+        // No IPv6 support on this machine
+        Err(_) => return,
+    };
+    let adr = listener.local_addr().unwrap();
+    let mut poll = Poll::new().unwrap();
+
+    let mut stream = TcpStream::connect(adr).unwrap();
+    wait_for_writable(&mut poll, &mut stream);
+
+    assert!(stream.take_error().unwrap().is_none());
+    assert_eq!(stream.peer_addr().unwrap(), adr);
+}
+
+#[test]
+fn refused_connection_is_reported_through_take_error() {
+    // Find a port nobody listens on
+    let adr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut poll = Poll::new().unwrap();
+
+    let mut stream = match TcpStream::connect(adr) {
+        Ok(stream) => stream,
+        // The kernel is allowed to fail a loopback connect right away
+        Err(e) => {
+            assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused);
+            return;
+        }
+    };
+    wait_for_writable(&mut poll, &mut stream);
+
+    let err = stream.take_error().unwrap().expect("expected an error");
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+}
@@ -0,0 +1,53 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, TcpListener, Trigger};
+use std::io::{self, Read, Write};
+use std::net;
+
+const LISTENER: usize = 0;
+
+#[test]
+fn accept_after_readable_event() {
+    let mut poll = Poll::new().unwrap();
+    let mut listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let adr = listener.local_addr().unwrap();
+    poll.registry()
+        .register(
+            &mut listener,
+            LISTENER,
+            Interests::READABLE,
+            Trigger::Oneshot,
+        )
+        .unwrap();
+
+    // Nothing to accept yet
+    let err = listener.accept().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+    let mut client = net::TcpStream::connect(adr).unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), LISTENER);
+    assert!(events[0].is_readable());
+
+    let (mut stream, peer_adr) = listener.accept().unwrap();
+    assert_eq!(peer_adr, client.local_addr().unwrap());
+
+    // Only one connection was pending
+    let err = listener.accept().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+    // The `WouldBlock` re-armed the listener, so the next connection is reported too
+    let _second = net::TcpStream::connect(adr).unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), LISTENER);
+    listener.accept().unwrap();
+
+    stream.write_all(b"hello").unwrap();
+    let mut buf = [0; 5];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+}
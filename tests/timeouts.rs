@@ -0,0 +1,114 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::connected_pair;
+use minimio::{Events, Interests, Poll, Trigger};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+#[test]
+fn poll_wakes_up_for_timeout() {
+    let mut poll = Poll::new().unwrap();
+    let start = Instant::now();
+    poll.insert_timeout(7, Duration::from_millis(50));
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, None).unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 7);
+    assert!(events[0].is_timeout());
+    assert!(!events[0].is_readable());
+
+    // A timeout only expires once
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+}
+
+#[test]
+fn timeouts_expire_in_order() {
+    let mut poll = Poll::new().unwrap();
+    // Far enough apart to end up on different levels of the wheel
+    poll.insert_timeout(3, Duration::from_millis(300));
+    poll.insert_timeout(1, Duration::from_millis(10));
+    poll.insert_timeout(2, Duration::from_millis(100));
+
+    let mut events = Events::with_capacity(16);
+    let mut expired = vec![];
+    while expired.len() < 3 {
+        poll.poll(&mut events, None).unwrap();
+        expired.extend(events.iter().map(|event| event.id()));
+    }
+
+    assert_eq!(expired, vec![1, 2, 3]);
+}
+
+#[test]
+fn cancelled_timeout_doesnt_expire() {
+    let mut poll = Poll::new().unwrap();
+    let cancelled = poll.insert_timeout(1, Duration::from_millis(20));
+    poll.insert_timeout(2, Duration::from_millis(40));
+    assert!(poll.cancel_timeout(cancelled));
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, None).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 2);
+    assert!(!poll.cancel_timeout(cancelled));
+}
+
+#[test]
+fn poll_timeout_still_applies() {
+    let mut poll = Poll::new().unwrap();
+    poll.insert_timeout(1, Duration::from_secs(10));
+
+    let start = Instant::now();
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(50)).unwrap();
+
+    assert!(events.is_empty());
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn longest_possible_timeout() {
+    let mut poll = Poll::new().unwrap();
+    let timeout = poll.insert_timeout(1, Duration::MAX);
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(50)).unwrap();
+    assert!(events.is_empty());
+    assert!(poll.cancel_timeout(timeout));
+}
+
+#[test]
+fn timeouts_share_the_buffer_with_io_events() {
+    let mut poll = Poll::new().unwrap();
+
+    let (mut stream, mut peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+    poll.insert_timeout(2, Duration::from_millis(0));
+    poll.insert_timeout(3, Duration::from_millis(0));
+    peer.write_all(b"hello").unwrap();
+    std::thread::sleep(Duration::from_millis(10));
+
+    // Only room for two, the last timeout is returned by the next poll
+    let mut events = Events::with_capacity(2);
+    poll.poll(&mut events, None).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_readable());
+    assert!(!events[0].is_timeout());
+    assert_eq!(events[1].id(), 2);
+    assert!(events[1].is_timeout());
+    assert!(!events[1].is_readable() && !events[1].is_writable() && !events[1].is_error());
+
+    poll.poll(&mut events, Some(0)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 3);
+    assert!(events[0].is_timeout());
+}
@@ -0,0 +1,129 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Clock, Events, Interests, Poll, Timer, Trigger};
+use std::io;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[test]
+fn one_shot_timeout() {
+    let mut poll = Poll::new().unwrap();
+    let mut timer = Timer::new(Clock::Monotonic).unwrap();
+    poll.registry()
+        .register(&mut timer, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let start = Instant::now();
+    timer.set_timeout(Duration::from_millis(50)).unwrap();
+    assert_eq!(
+        timer.expirations().unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_readable());
+    assert_eq!(timer.expirations().unwrap(), 1);
+
+    // It doesn't fire again
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+}
+
+#[test]
+fn periodic_timer_counts_expirations() {
+    let mut poll = Poll::new().unwrap();
+    let mut timer = Timer::new(Clock::Monotonic).unwrap();
+    poll.registry()
+        .register(&mut timer, 2, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    timer.set_interval(Duration::from_millis(20)).unwrap();
+    std::thread::sleep(Duration::from_millis(70));
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(timer.expirations().unwrap() >= 3);
+
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(timer.expirations().unwrap() >= 1);
+}
+
+#[test]
+fn setting_a_timer_again_after_it_fired() {
+    let mut poll = Poll::new().unwrap();
+    let mut timer = Timer::new(Clock::Monotonic).unwrap();
+    poll.registry()
+        .register(&mut timer, 6, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    timer.set_timeout(Duration::from_millis(10)).unwrap();
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+
+    // We never read the expiration, setting the timer is enough to get the next event
+    timer.set_timeout(Duration::from_millis(10)).unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 6);
+    assert_eq!(timer.expirations().unwrap(), 1);
+}
+
+#[test]
+fn absolute_monotonic_deadline() {
+    let mut poll = Poll::new().unwrap();
+    let mut timer = Timer::new(Clock::Monotonic).unwrap();
+    poll.registry()
+        .register(&mut timer, 3, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let deadline = timer.now().unwrap() + Duration::from_millis(30);
+    timer.set_deadline(deadline, None).unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(timer.now().unwrap() >= deadline);
+    assert_eq!(timer.expirations().unwrap(), 1);
+}
+
+#[test]
+fn absolute_realtime_deadline_in_the_past_fires_right_away() {
+    let mut poll = Poll::new().unwrap();
+    let mut timer = Timer::new(Clock::Realtime).unwrap();
+    poll.registry()
+        .register(&mut timer, 4, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    timer
+        .set_deadline(now - Duration::from_secs(1), None)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 4);
+    assert_eq!(timer.expirations().unwrap(), 1);
+}
+
+#[test]
+fn disarmed_timer_never_fires() {
+    let mut poll = Poll::new().unwrap();
+    let mut timer = Timer::new(Clock::Monotonic).unwrap();
+    poll.registry()
+        .register(&mut timer, 5, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    timer.set_timeout(Duration::from_millis(20)).unwrap();
+    timer.disarm().unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+}
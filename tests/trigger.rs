@@ -0,0 +1,50 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::connected_pair;
+use minimio::{Events, Interests, Poll, Trigger};
+use std::io::Write;
+
+#[test]
+fn level_triggered_reports_until_consumed() {
+    let mut poll = Poll::new().unwrap();
+    let (mut stream, mut peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Level)
+        .unwrap();
+
+    peer.write_all(b"data").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    for _ in 0..3 {
+        poll.poll(&mut events, Some(1000)).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id(), 1);
+    }
+}
+
+#[test]
+fn edge_triggered_reports_each_change_and_stays_armed() {
+    let mut poll = Poll::new().unwrap();
+    let (mut stream, mut peer) = connected_pair();
+    poll.registry()
+        .register(&mut stream, 1, Interests::READABLE, Trigger::Edge)
+        .unwrap();
+
+    peer.write_all(b"first").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+
+    // No new data arrived so there is no new edge, even though the data is still unread
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+
+    // The registration is still armed without calling `reregister`
+    peer.write_all(b"second").unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+}
@@ -0,0 +1,83 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, Trigger, UdpSocket};
+use std::io;
+
+#[test]
+fn send_to_and_recv_from_through_poll() {
+    let mut poll = Poll::new().unwrap();
+    let mut receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    poll.registry()
+        .register(&mut receiver, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    let mut buf = [0; 64];
+
+    for datagram in [&b"first"[..], &b"second"[..]].iter() {
+        sender
+            .send_to(datagram, receiver.local_addr().unwrap())
+            .unwrap();
+
+        poll.poll(&mut events, Some(1000)).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id(), 1);
+        assert!(events[0].is_readable());
+
+        let (n, from) = receiver.peek_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], *datagram);
+        assert_eq!(from, sender.local_addr().unwrap());
+
+        let (n, from) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], *datagram);
+        assert_eq!(from, sender.local_addr().unwrap());
+
+        // Re-arms the registration for the next datagram
+        let err = receiver.recv_from(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+}
+
+#[test]
+fn connected_send_and_recv() {
+    let mut poll = Poll::new().unwrap();
+    let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut b = UdpSocket::bind("127.0.0.1:0").unwrap();
+    a.connect(b.local_addr().unwrap()).unwrap();
+    b.connect(a.local_addr().unwrap()).unwrap();
+    assert_eq!(a.peer_addr().unwrap(), b.local_addr().unwrap());
+
+    poll.registry()
+        .register(
+            &mut b,
+            2,
+            Interests::READABLE | Interests::WRITABLE,
+            Trigger::Level,
+        )
+        .unwrap();
+
+    a.send(b"ping").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_readable());
+    assert!(events[0].is_writable());
+
+    let mut buf = [0; 16];
+    let n = b.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(
+        b.recv(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+}
+
+#[test]
+fn broadcast_option() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert!(!socket.broadcast().unwrap());
+    socket.set_broadcast(true).unwrap();
+    assert!(socket.broadcast().unwrap());
+}
@@ -0,0 +1,238 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{
+    Events, Interests, Poll, Trigger, UnixDatagram, UnixListener, UnixSeqpacketListener, UnixStream,
+};
+use std::io::{self, Read, Write};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::SocketAddr;
+use std::path::PathBuf;
+use std::process;
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("minimio-{}-{}.sock", process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn abstract_addr(name: &str) -> SocketAddr {
+    let name = format!("minimio-{}-{}", process::id(), name);
+    SocketAddr::from_abstract_name(name.as_bytes()).unwrap()
+}
+
+#[test]
+fn stream_pair_is_registrable() {
+    let mut poll = Poll::new().unwrap();
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    poll.registry()
+        .register(&mut b, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut buf = [0; 16];
+    assert_eq!(
+        b.read(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    a.write_all(b"hello").unwrap();
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_readable());
+
+    let n = b.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"hello");
+}
+
+#[test]
+fn full_stream_becomes_writable_again() {
+    let mut poll = Poll::new().unwrap();
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    poll.registry()
+        .register(&mut a, 1, Interests::WRITABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_writable());
+
+    // Fill the socket, the `WouldBlock` re-arms the registration
+    let chunk = [0; 4096];
+    loop {
+        match a.write(&chunk) {
+            Ok(_) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => panic!("write err: {}", e),
+        }
+    }
+
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match b.read(&mut buf) {
+            Ok(n) => assert!(n > 0),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => panic!("read err: {}", e),
+        }
+    }
+
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_writable());
+}
+
+#[test]
+fn listener_accepts_path_connections() {
+    let path = socket_path("listener");
+    let mut poll = Poll::new().unwrap();
+    let mut listener = UnixListener::bind(&path).unwrap();
+    poll.registry()
+        .register(&mut listener, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    assert_eq!(
+        listener.accept().unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    let mut client = UnixStream::connect(&path).unwrap();
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+
+    let (mut server, _) = listener.accept().unwrap();
+    assert_eq!(
+        client.peer_addr().unwrap().as_pathname(),
+        Some(path.as_path())
+    );
+    assert_eq!(
+        listener.local_addr().unwrap().as_pathname(),
+        Some(path.as_path())
+    );
+
+    client.write_all(b"ping").unwrap();
+    let mut buf = [0; 16];
+    let n = server.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"ping");
+
+    // The `WouldBlock` re-arms the listener, so the next connection is reported too
+    assert_eq!(
+        listener.accept().unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+    let _second = UnixStream::connect(&path).unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    listener.accept().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn datagrams_in_the_abstract_namespace() {
+    let mut poll = Poll::new().unwrap();
+    let addr = abstract_addr("datagram");
+    let mut receiver = UnixDatagram::bind_addr(&addr).unwrap();
+    let sender_addr = abstract_addr("datagram-sender");
+    let sender = UnixDatagram::bind_addr(&sender_addr).unwrap();
+    poll.registry()
+        .register(&mut receiver, 2, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    sender.send_to_addr(b"one", &addr).unwrap();
+    sender.send_to_addr(b"two", &addr).unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 2);
+
+    let mut buf = [0; 16];
+    let (n, from) = receiver.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"one");
+    assert_eq!(from.as_abstract_name(), sender_addr.as_abstract_name());
+    let (n, _) = receiver.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"two");
+    assert_eq!(
+        receiver.recv_from(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+}
+
+#[test]
+fn seqpacket_pair_keeps_message_boundaries() {
+    let mut poll = Poll::new().unwrap();
+    let (a, mut b) = UnixDatagram::seqpacket_pair().unwrap();
+    poll.registry()
+        .register(&mut b, 3, Interests::READABLE, Trigger::Level)
+        .unwrap();
+
+    a.send(b"first").unwrap();
+    a.send(b"second").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 3);
+
+    let mut buf = [0; 16];
+    let n = b.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"first");
+    let n = b.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"second");
+}
+
+#[test]
+fn seqpacket_listener_in_the_abstract_namespace() {
+    let mut poll = Poll::new().unwrap();
+    let addr = abstract_addr("seqpacket");
+    let mut listener = UnixSeqpacketListener::bind_addr(&addr).unwrap();
+    assert_eq!(
+        listener.local_addr().unwrap().as_abstract_name(),
+        addr.as_abstract_name()
+    );
+    poll.registry()
+        .register(&mut listener, 4, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+
+    let client = UnixDatagram::connect_seqpacket(&addr).unwrap();
+    assert_eq!(
+        client.peer_addr().unwrap().as_abstract_name(),
+        addr.as_abstract_name()
+    );
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 4);
+
+    let (server, _) = listener.accept().unwrap();
+    client.send(b"message").unwrap();
+
+    let mut buf = [0; 4];
+    // The part of a message that doesn't fit in the buffer is discarded
+    let n = server.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"mess");
+    assert_eq!(
+        server.recv(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    // The `WouldBlock` re-arms the listener, so the next connection is reported too
+    assert_eq!(
+        listener.accept().unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+    let _second = UnixDatagram::connect_seqpacket(&addr).unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 4);
+    listener.accept().unwrap();
+}
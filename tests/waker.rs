@@ -0,0 +1,45 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Poll, Waker};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const WAKE_TOKEN: usize = 10;
+
+#[test]
+fn waker_wakes_poll_many_times() {
+    let mut poll = Poll::new().unwrap();
+    let waker = Arc::new(Waker::new(poll.registry(), WAKE_TOKEN).unwrap());
+    let mut events = Events::with_capacity(16);
+
+    for _ in 0..3 {
+        let waker = waker.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            waker.wake().expect("wake err.");
+        });
+
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id(), WAKE_TOKEN);
+        assert!(events[0].is_readable());
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn waker_does_not_spin() {
+    let mut poll = Poll::new().unwrap();
+    let waker = Waker::new(poll.registry(), WAKE_TOKEN).unwrap();
+    let mut events = Events::with_capacity(16);
+
+    waker.wake().unwrap();
+    waker.wake().unwrap();
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+
+    // Nothing happened since the last poll so we should time out
+    poll.poll(&mut events, Some(100)).unwrap();
+    assert!(events.is_empty());
+}
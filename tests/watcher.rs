@@ -0,0 +1,135 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, Trigger, WatchEvent, WatchEventKind, WatchMask, Watcher};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("minimio-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+/// Waits for a readable event and reads until `WouldBlock`.
+fn next_events(poll: &mut Poll, watcher: &mut Watcher) -> Vec<WatchEvent> {
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_readable());
+
+    let mut watch_events = vec![];
+    loop {
+        match watcher.read_events() {
+            Ok(events) => watch_events.extend(events),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => panic!("read err: {}", e),
+        }
+    }
+    watch_events
+}
+
+#[test]
+fn reports_typed_events() {
+    let dir = temp_dir("typed");
+    let mut poll = Poll::new().unwrap();
+    let mut watcher = Watcher::new().unwrap();
+    poll.registry()
+        .register(&mut watcher, 1, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+    let wd = watcher
+        .add_watch(
+            &dir,
+            WatchMask::CREATE | WatchMask::MODIFY | WatchMask::DELETE,
+        )
+        .unwrap();
+
+    fs::write(dir.join("config"), b"a = 1").unwrap();
+    let events = next_events(&mut poll, &mut watcher);
+    assert_eq!(events[0].wd, wd);
+    assert_eq!(events[0].kind, WatchEventKind::Created);
+    assert_eq!(events[0].name.as_deref(), Some(Path::new("config")));
+    assert!(!events[0].is_dir);
+    assert_eq!(events[1].kind, WatchEventKind::Modified);
+
+    fs::create_dir(dir.join("sub")).unwrap();
+    let events = next_events(&mut poll, &mut watcher);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, WatchEventKind::Created);
+    assert!(events[0].is_dir);
+
+    fs::remove_file(dir.join("config")).unwrap();
+    let events = next_events(&mut poll, &mut watcher);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, WatchEventKind::Deleted);
+    assert_eq!(events[0].name.as_deref(), Some(Path::new("config")));
+
+    watcher.remove_watch(wd).unwrap();
+    let events = next_events(&mut poll, &mut watcher);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, WatchEventKind::Removed);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn pairs_moves_by_cookie() {
+    let dir = temp_dir("moves");
+    let other = temp_dir("moves-unwatched");
+    let mut poll = Poll::new().unwrap();
+    let mut watcher = Watcher::new().unwrap();
+    poll.registry()
+        .register(&mut watcher, 2, Interests::READABLE, Trigger::Oneshot)
+        .unwrap();
+    fs::create_dir(dir.join("a")).unwrap();
+    fs::create_dir(dir.join("b")).unwrap();
+    fs::write(dir.join("a").join("log"), b"").unwrap();
+    let a = watcher.add_watch(dir.join("a"), WatchMask::MOVE).unwrap();
+    let b = watcher.add_watch(dir.join("b"), WatchMask::MOVE).unwrap();
+
+    fs::rename(dir.join("a").join("log"), dir.join("b").join("log.1")).unwrap();
+    let events = next_events(&mut poll, &mut watcher);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].wd, b);
+    assert_eq!(events[0].name.as_deref(), Some(Path::new("log.1")));
+    assert_eq!(
+        events[0].kind,
+        WatchEventKind::Moved {
+            from_wd: a,
+            from_name: Some(PathBuf::from("log")),
+        }
+    );
+
+    // Only one end of the move is watched
+    fs::rename(dir.join("b").join("log.1"), other.join("log.1")).unwrap();
+    let events = next_events(&mut poll, &mut watcher);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].wd, b);
+    let cookie = match events[0].kind {
+        WatchEventKind::MovedFrom { cookie } => cookie,
+        ref kind => panic!("unexpected event: {:?}", kind),
+    };
+
+    fs::rename(other.join("log.1"), dir.join("a").join("log")).unwrap();
+    let events = next_events(&mut poll, &mut watcher);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].wd, a);
+    match events[0].kind {
+        WatchEventKind::MovedTo { cookie: to } => assert_ne!(to, cookie),
+        ref kind => panic!("unexpected event: {:?}", kind),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&other).unwrap();
+}
+
+#[test]
+fn watching_a_missing_path_fails() {
+    let watcher = Watcher::new().unwrap();
+    let err = watcher
+        .add_watch("/this/path/does/not/exist", WatchMask::MODIFY)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
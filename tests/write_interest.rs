@@ -0,0 +1,76 @@
+#![cfg(target_os = "linux")]
+
+use minimio::{Events, Interests, Poll, TcpStream, Trigger};
+use std::io::Write;
+use std::net::TcpListener;
+
+#[test]
+fn writable_event_on_connected_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    registrator
+        .register(&mut stream, 1, Interests::WRITABLE, Trigger::Oneshot)
+        .expect("registration err.");
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 1);
+    assert!(events[0].is_writable());
+    assert!(!events[0].is_readable());
+}
+
+#[test]
+fn readable_event_reports_direction() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut peer, _) = listener.accept().unwrap();
+    registrator
+        .register(&mut stream, 2, Interests::READABLE, Trigger::Oneshot)
+        .expect("registration err.");
+
+    peer.write_all(b"hello").unwrap();
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 2);
+    assert!(events[0].is_readable());
+    assert!(!events[0].is_writable());
+}
+
+#[test]
+fn combined_registration_reports_both_directions() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut poll = Poll::new().unwrap();
+    let registrator = poll.registrator();
+
+    let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut peer, _) = listener.accept().unwrap();
+    peer.write_all(b"hello").unwrap();
+
+    registrator
+        .register(
+            &mut stream,
+            3,
+            Interests::READABLE | Interests::WRITABLE,
+            Trigger::Oneshot,
+        )
+        .expect("registration err.");
+
+    let mut events = Events::with_capacity(16);
+    poll.poll(&mut events, Some(1000)).unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), 3);
+    assert!(events[0].is_readable());
+    assert!(events[0].is_writable());
+}